@@ -1,217 +1,597 @@
 use fs::File;
 use io::{BufRead, Read, Write};
 use path::{Path, PathBuf};
+use std::collections::BTreeMap;
 use std::env::var_os;
-use std::{env, fmt, fs, io, path};
+use std::ffi::OsString;
+use std::process::{self, Command, Stdio};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use std::{env, fs, io, path};
 
-enum Error {
-    GitDirNotFound,
-    Io(io::Error),
-    OutDir(env::VarError),
-    InvalidUserHooksDir(PathBuf),
-    EmptyUserHook(PathBuf),
+// Debug log is written to stderr when `$CARGO_HUSKY_LOG` is `debug`. Run `cargo test -vv` to see it.
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if env::var("CARGO_HUSKY_LOG").map(|v| v == "debug").unwrap_or(false) {
+            eprintln!("cargo-husky: [debug] {}", format!($($arg)*));
+        }
+    };
 }
 
-type Result<T> = std::result::Result<T, Error>;
+// Modules are shared with the library. Some items are only used by the library.
+#[allow(dead_code)]
+#[path = "src/backup.rs"]
+mod backup;
+#[path = "src/config.rs"]
+mod config;
+#[path = "src/error.rs"]
+mod error;
+#[path = "src/export.rs"]
+mod export;
+#[path = "src/features.rs"]
+mod features;
+#[path = "src/git.rs"]
+mod git;
+#[allow(dead_code)]
+#[path = "src/hook.rs"]
+mod hook;
+#[allow(dead_code)]
+#[path = "src/plan.rs"]
+mod plan;
+#[path = "src/script.rs"]
+mod script;
+#[allow(dead_code)]
+#[path = "src/toml.rs"]
+mod toml;
+#[path = "src/user_config.rs"]
+mod user_config;
+#[allow(dead_code)]
+#[path = "src/verify.rs"]
+mod verify;
 
-impl From<io::Error> for Error {
-    fn from(error: io::Error) -> Error {
-        Error::Io(error)
-    }
+use backup::backup_hook;
+use config::{find_manifest, Config};
+use error::{Error, Result};
+use export::render_pre_commit_config;
+use git::{
+    common_dir, env_overrides_discovery, file_mode_enabled, find_gitdir, find_work_tree, hooks_dir,
+    path_from_bytes, work_tree, worktree_config_enabled,
+};
+use hook::{
+    chained_hook_path, generated_hooks, hook_action, hook_manager, hook_state, is_generated_hook,
+    merged_hook_path, permission_bits, put_managed_block, remove_managed_block, stale_hooks,
+    write_executable_file, HookAction, HookState, DEFAULT_HOOK_MODE, MANAGED_BLOCK_BEGIN,
+    MANAGED_BLOCK_END,
+};
+use plan::{plan_path, Step};
+use user_config::{remote_urls, user_config_path, UserConfig};
+use verify::{record_path, InstallRecord};
+
+fn resolve_gitdir() -> Result<PathBuf> {
+    let dir = env::var("OUT_DIR")?;
+    debug!(
+        "Finding .git directory from {:?} ($GIT_DIR={:?}, $GIT_WORK_TREE={:?})",
+        dir,
+        var_os("GIT_DIR"),
+        var_os("GIT_WORK_TREE"),
+    );
+    let gitdir = find_gitdir(Path::new(&dir)).map_err(|err| {
+        debug!("Repository was not found: {}", err);
+        err
+    })?;
+    debug!("Found .git directory {:?}", gitdir);
+    Ok(gitdir)
 }
 
-impl From<env::VarError> for Error {
-    fn from(error: env::VarError) -> Error {
-        Error::OutDir(error)
+// Root of the working tree of `gitdir`. It is found from OUT_DIR again since a Git directory
+// separated from its working tree (e.g. `git init --separate-git-dir`) does not know it.
+fn resolve_work_tree(gitdir: &Path) -> PathBuf {
+    let out_dir = env::var_os("OUT_DIR").unwrap_or_default();
+    let start = Path::new(&out_dir);
+    match (find_gitdir(start), find_work_tree(start)) {
+        (Ok(found), Ok(root)) if found == gitdir => root,
+        _ => work_tree(gitdir).to_owned(),
     }
 }
 
-impl fmt::Debug for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let msg = match self {
-            Error::GitDirNotFound => format!(
-                ".git directory was not found in '{}' or its parent directories",
-                env::var("OUT_DIR").unwrap_or_else(|_| "".to_string()),
-            ),
-            Error::Io(inner) => format!("IO error: {}", inner),
-            Error::OutDir(env::VarError::NotPresent) => unreachable!(),
-            Error::OutDir(env::VarError::NotUnicode(msg)) => msg.to_string_lossy().to_string(),
-            Error::InvalidUserHooksDir(path) => {
-                format!("User hooks directory is not found or no executable file is found in '{:?}'. Did you forget to make a hook script executable?", path)
-	        }
-            Error::EmptyUserHook(path) => format!("User hook script is empty: {:?}", path),
-        };
-        write!(f, "{}", msg)
-    }
+// Features of cargo-husky enabled by the project
+fn enabled_features() -> Vec<String> {
+    let mut features = env::vars()
+        .filter_map(|(k, _)| {
+            k.strip_prefix("CARGO_FEATURE_")
+                .map(|f| f.to_lowercase().replace('_', "-"))
+        })
+        .collect::<Vec<_>>();
+    features.sort();
+    features
 }
 
-fn resolve_gitdir() -> Result<PathBuf> {
-    let dir = env::var("OUT_DIR")?;
-    let mut dir = PathBuf::from(dir);
-    if !dir.has_root() {
-        dir = fs::canonicalize(dir)?;
-    }
-    loop {
-        let gitdir = dir.join(".git");
-        if gitdir.is_dir() {
-            return Ok(gitdir);
-        }
-        if gitdir.is_file() {
-            let mut buf = String::new();
-            File::open(gitdir)?.read_to_string(&mut buf)?;
-            let newlines: &[_] = &['\n', '\r'];
-            let gitdir = PathBuf::from(buf.trim_end_matches(newlines));
-            if !gitdir.is_dir() {
-                return Err(Error::GitDirNotFound);
+fn load_config(gitdir: &Path) -> Result<Config> {
+    let out_dir = env::var("OUT_DIR")?;
+    let root = resolve_work_tree(gitdir);
+    let config = match find_manifest(Path::new(&out_dir), &root) {
+        Some(manifest) => {
+            debug!("Loading configuration from {:?}", manifest);
+            // Hooks are generated again when the configuration is edited
+            println!("cargo:rerun-if-changed={}", manifest.display());
+            Config::load(&manifest)?
+        }
+        None => {
+            debug!("Cargo.toml was not found. Using default configuration");
+            Config::default()
+        }
+    };
+    Ok(config.with_features(enabled_features()))
+}
+
+// `user` means the user hook script was installed as `{hook}.user` to be run by the generated hook
+fn install_hook(
+    hook: &str,
+    hooks_dir: &Path,
+    gitdir: &Path,
+    config: &Config,
+    user: bool,
+) -> Result<()> {
+    let hook_path = hooks_dir.join(hook);
+    // Commands are updated even if the hook script is up-to-date
+    if cfg!(feature = "native-runner") {
+        let path = plan_path(hook_path.parent().unwrap(), hook);
+        if dry_run() {
+            would("write commands to", &path);
+        } else {
+            script::plan(hook, config)?.save(&path, hook)?;
+        }
+    }
+    let root = resolve_work_tree(gitdir);
+    let manager = hook_manager(&root, &hook_path);
+    let mode = manager_mode(config)?;
+    let perms = hook_mode(config)?;
+    if let Some(manager) = manager {
+        debug!("Hook {:?} is managed by {}", hook_path, manager);
+        match mode {
+            ManagerMode::Defer => {
+                println!(
+                    "cargo:warning=cargo-husky: Hook {:?} was not installed since it is managed by {}. Set 'hook-manager' in [package.metadata.husky] to \"merge\" or \"override\" to install it",
+                    hook_path, manager
+                );
+                return Ok(());
+            }
+            ManagerMode::Merge if hook_path.is_file() => {
+                // `$0.user` and `$0.local` are not available since the script is renamed
+                let script = script::render(hook, false, false, config)?;
+                return merge_hook(hook, &hook_path, manager, &script, config);
             }
-            return Ok(gitdir);
+            _ => {}
+        }
+    }
+    let chained_path = chained_hook_path(&hook_path);
+    let action = if manager.is_some() && mode == ManagerMode::Override && hook_path.exists() {
+        HookAction::Overwrite
+    } else {
+        hook_action(&hook_path, cfg!(feature = "chain-hooks"))
+    };
+    debug!("Hook {:?}: {}", hook_path, action);
+    // The existing hook is not renamed actually on dry run
+    let chained =
+        cfg!(feature = "chain-hooks") && (chained_path.is_file() || action == HookAction::Chain);
+    let script = script::render(hook, chained, user, config)?;
+    match action {
+        // The hook is not rewritten when its content is identical to avoid changing its mtime
+        HookAction::Keep if is_same_content(&hook_path, &script, perms) => return keep(&hook_path),
+        // The configuration was changed or the hook was edited by hand
+        HookAction::Keep | HookAction::Overwrite => backup(&hook_path)?,
+        HookAction::SkipForeign => return hook_not_installed(Error::ForeignHook(hook_path)),
+        HookAction::SkipNewer => return keep_newer(&hook_path),
+        HookAction::SkipChainConflict => {
+            return hook_not_installed(Error::ChainConflict(chained_path));
         }
-        if !dir.pop() {
-            return Err(Error::GitDirNotFound);
+        HookAction::Chain => {
+            backup(&hook_path)?;
+            rename(&hook_path, &chained_path)?;
         }
+        HookAction::Create => {}
     }
+    write_hook(&hook_path, &script, perms)?;
+    let steps = script::steps(hook, config)?;
+    report_installed(hook, &steps.iter().map(command_summary).collect::<Vec<_>>());
+    Ok(())
 }
 
-// This function returns true when
-//   - the hook was generated by the same version of cargo-husky
-//   - someone else had already put another hook script
-// For safety, cargo-husky does nothing on case2 also.
-fn hook_already_exists(hook: &Path) -> bool {
-    let f = match File::open(hook) {
-        Ok(f) => f,
-        Err(..) => return false,
+// What to do with hooks managed by another hook manager such as lefthook
+#[derive(Clone, Copy, PartialEq)]
+enum ManagerMode {
+    // Do not install the hook
+    Defer,
+    // Run the generated hook from the managed block in the hook script of the manager
+    Merge,
+    // Back up the hook script of the manager and replace it
+    Override,
+}
+
+fn manager_mode(config: &Config) -> Result<ManagerMode> {
+    match config.get_str("hook-manager")? {
+        None | Some("defer") => Ok(ManagerMode::Defer),
+        Some("merge") => Ok(ManagerMode::Merge),
+        Some("override") => Ok(ManagerMode::Override),
+        Some(_) => Err(Error::InvalidConfig(
+            "hook-manager".to_string(),
+            "\"defer\", \"merge\" or \"override\"",
+            "other string",
+        )),
+    }
+}
+
+// Permissions of installed hook scripts (`hook-mode`). They must allow the owner to read and run the
+// scripts since Git ignores hooks which are not executable.
+fn hook_mode(config: &Config) -> Result<u32> {
+    let mode = match config.get_str("hook-mode")? {
+        Some(mode) => mode,
+        None => return Ok(DEFAULT_HOOK_MODE),
     };
+    match u32::from_str_radix(mode.strip_prefix("0o").unwrap_or(mode), 8) {
+        Ok(mode) if mode <= 0o777 && mode & 0o500 == 0o500 => Ok(mode),
+        _ => Err(Error::InvalidConfig(
+            "hook-mode".to_string(),
+            "octal permissions which allow the owner to read and execute such as \"0750\"",
+            "other string",
+        )),
+    }
+}
 
-    let ver_line = match io::BufReader::new(f).lines().nth(2) {
-        None => return true, // Less than 2 lines. The hook script seemed to be generated by someone else
-        Some(Err(..)) => return false, // Failed to read entry. Re-generate anyway
-        Some(Ok(line)) => line,
+// Put the generated hook script next to the hook script of another hook manager and run it from
+// the managed block in the manager's script. The block is put again when the manager rewrites its
+// script.
+fn merge_hook(
+    hook: &str,
+    hook_path: &Path,
+    manager: &str,
+    script: &str,
+    config: &Config,
+) -> Result<()> {
+    let merged_path = merged_hook_path(hook_path);
+    let name = merged_path.file_name().unwrap().to_string_lossy();
+    let existing = fs::read_to_string(hook_path)?;
+    let merged = put_managed_block(
+        Some(&existing),
+        &script::render_managed_block(&name),
+        config,
+    )?;
+    let mode = hook_mode(config)?;
+    if is_same_content(&merged_path, script, mode) && existing == merged {
+        return keep(hook_path);
+    }
+    write_hook(&merged_path, script, mode)?;
+    if existing != merged {
+        backup(hook_path)?;
+        // The script of the manager keeps its permissions
+        let mode = permission_bits(hook_path).unwrap_or(mode);
+        write_hook(hook_path, &merged, mode)?;
+    }
+    let steps = script::steps(hook, config)?;
+    report_installed(
+        &format!("{} into {} hook", hook, manager),
+        &steps.iter().map(command_summary).collect::<Vec<_>>(),
+    );
+    Ok(())
+}
+
+// Command line of the step without arguments passed to the tool after `--`
+fn command_summary(step: &Step) -> String {
+    step.args
+        .iter()
+        .take_while(|a| a.as_str() != "--")
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Tell users which hook was installed since `.git/hooks` is changed silently otherwise
+fn report_installed(hook: &str, details: &[String]) {
+    let verb = if dry_run() {
+        "Would install"
+    } else {
+        "Installed"
     };
+    if details.is_empty() {
+        println!("cargo:warning=cargo-husky: {} {}", verb, hook);
+    } else {
+        println!(
+            "cargo:warning=cargo-husky: {} {} ({})",
+            verb,
+            hook,
+            details.join(", ")
+        );
+    }
+}
+
+// Nothing is written to `.git` when `$CARGO_HUSKY_DRY_RUN` is set. Changes are only reported.
+fn dry_run() -> bool {
+    var_os("CARGO_HUSKY_DRY_RUN").is_some()
+}
+
+fn would(action: &str, path: &Path) {
+    println!("cargo:warning=cargo-husky: Would {} {:?}", action, path);
+}
+
+fn keep<T: Default>(path: &Path) -> Result<T> {
+    if dry_run() {
+        would("keep up-to-date hook", path);
+    }
+    Ok(T::default())
+}
+
+// A hook generated by a newer version is never downgraded by an older version pinned somewhere
+// else (e.g. by another workspace member)
+fn keep_newer<T: Default>(path: &Path) -> Result<T> {
+    println!(
+        "cargo:warning=cargo-husky: {}",
+        Error::NewerHook(path.to_owned())
+    );
+    Ok(T::default())
+}
 
-    if !ver_line.contains("This hook was set by cargo-husky") {
-        // The hook script was generated by someone else.
-        true
+// Hooks are always written with LF line endings. Sources checked out with CRLF (e.g. by
+// `core.autocrlf`) would make the shebang `/bin/sh^M` which is not found
+fn normalize_line_endings(content: &str) -> String {
+    content.replace("\r\n", "\n")
+}
+
+// The hook is written again when it has permissions which are not allowed by `mode` so that
+// `hook-mode` takes effect on hooks installed before
+fn is_same_content(path: &Path, content: &str, mode: u32) -> bool {
+    permission_bits(path).map_or(true, |bits| bits & !mode == 0)
+        && fs::read(path)
+            .map(|c| c == normalize_line_endings(content).as_bytes())
+            .unwrap_or(false)
+}
+
+fn write_hook(path: &Path, content: &str, mode: u32) -> Result<()> {
+    if dry_run() {
+        would("write", path);
+        return Ok(());
+    }
+    write_executable_file(path, normalize_line_endings(content).as_bytes(), mode)?;
+    Ok(())
+}
+
+fn backup(path: &Path) -> Result<()> {
+    if dry_run() {
+        would("back up", path);
     } else {
-        let ver_comment = format!(
-            "This hook was set by cargo-husky v{}",
-            env!("CARGO_PKG_VERSION")
+        backup_hook(path)?;
+    }
+    Ok(())
+}
+
+fn rename(from: &Path, to: &Path) -> Result<()> {
+    if dry_run() {
+        println!(
+            "cargo:warning=cargo-husky: Would rename {:?} to {:?}",
+            from, to
         );
-        ver_line.contains(&ver_comment)
+    } else {
+        fs::rename(from, to)?;
     }
+    Ok(())
 }
 
-fn write_script<W: io::Write>(w: &mut W) -> Result<()> {
-    macro_rules! raw_cmd {
-        ($c:expr) => {
-            concat!("\necho '+", $c, "'\n", $c)
-        };
+fn remove(path: &Path) -> Result<()> {
+    if dry_run() {
+        would("remove", path);
+    } else {
+        fs::remove_file(path)?;
     }
+    Ok(())
+}
 
-    #[cfg(feature = "run-for-all")]
-    macro_rules! cmd {
-        ($c:expr) => {
-            raw_cmd!(concat!($c, " --all"))
-        };
-        ($c:expr, $subflags:expr) => {
-            raw_cmd!(concat!($c, " --all -- ", $subflags))
-        };
+// Variables substituted for placeholders such as `{{crate_name}}` in user hook scripts. Values are
+// `OsString` since `{{workspace_root}}` may not be valid UTF-8.
+type TemplateVars = BTreeMap<String, OsString>;
+
+fn template_vars(config: &Config, gitdir: &Path) -> Result<TemplateVars> {
+    let mut vars = TemplateVars::new();
+    for (name, value) in config.get_str_table("vars")?.unwrap_or_default() {
+        vars.insert(name.to_string(), value.into());
+    }
+    // Built-in variables cannot be overridden
+    if let Some(name) = config.package_name() {
+        vars.insert("crate_name".to_string(), name.into());
     }
+    let root = config
+        .manifest_dir()
+        .map(Path::to_owned)
+        .unwrap_or_else(|| resolve_work_tree(gitdir));
+    vars.insert("workspace_root".to_string(), root.into_os_string());
+    vars.insert(
+        "cargo_husky_version".to_string(),
+        env!("CARGO_PKG_VERSION").into(),
+    );
+    Ok(vars)
+}
 
-    #[cfg(not(feature = "run-for-all"))]
-    macro_rules! cmd {
-        ($c:expr) => {
-            raw_cmd!($c)
-        };
-        ($c:expr, $subflags:expr) => {
-            raw_cmd!(concat!($c, " -- ", $subflags))
+// Substitute placeholders such as `{{crate_name}}` with the variables. Unknown placeholders are
+// kept as-is. It fails only when the value of a placeholder in the line is not valid UTF-8.
+fn expand_template(line: &str, vars: &TemplateVars) -> Result<String> {
+    let mut expanded = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(len) => start + len,
+            None => break,
         };
+        expanded.push_str(&rest[..start]);
+        match vars.get(rest[start + 2..end].trim()) {
+            Some(value) => match value.to_str() {
+                Some(value) => expanded.push_str(value),
+                None => return Err(Error::NonUtf8Path(PathBuf::from(value))),
+            },
+            None => expanded.push_str(&rest[start..end + 2]),
+        }
+        rest = &rest[end + 2..];
     }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
 
-    let script = {
-        let mut s = String::new();
-        if cfg!(feature = "run-cargo-test") {
-            s += cmd!("cargo test");
-        }
-        if cfg!(feature = "run-cargo-check") {
-            s += cmd!("cargo check");
-        }
-        if cfg!(feature = "run-cargo-clippy") {
-            s += cmd!("cargo clippy", "-D warnings");
-        }
-        if cfg!(feature = "run-cargo-fmt") {
-            s += cmd!("cargo fmt", "--check");
-        }
-        s
-    };
+// Things shared by user hook scripts on installing them
+struct UserHookContext {
+    vars: TemplateVars,
+    // `.cargo-husky` directory
+    husky_dir: PathBuf,
+    // `core.fileMode` of the repository
+    file_mode: bool,
+    // Permissions of installed scripts (`hook-mode`)
+    mode: u32,
+    config: Config,
+}
 
-    writeln!(
-        w,
-        r#"#!/bin/sh
-#
-# This hook was set by cargo-husky v{}: {}
-# Generated by script {}{}build.rs
-# Output at {}
-#
-
-set -e
-{}"#,
-        env!("CARGO_PKG_VERSION"),
-        env!("CARGO_PKG_HOMEPAGE"),
-        env!("CARGO_MANIFEST_DIR"),
-        path::MAIN_SEPARATOR,
-        env::var("OUT_DIR").unwrap_or_else(|_| "".to_string()),
-        script
-    )?;
-    Ok(())
+// Read lines of the user hook script. `# husky-include: lib/foo.sh` line is followed by lines of
+// the file in `.cargo-husky/lib` directory so that hook scripts can share helper functions.
+fn read_user_hook_lines(
+    src: &Path,
+    ctx: &UserHookContext,
+    including: &mut Vec<PathBuf>,
+) -> Result<Vec<String>> {
+    let mut lines = vec![];
+    for line in io::BufReader::new(File::open(src)?).lines() {
+        let line = line?;
+        let include = match line.trim_start().strip_prefix("# husky-include:") {
+            Some(path) => path.trim().to_string(),
+            None => {
+                lines.push(expand_template(&line, &ctx.vars)?);
+                continue;
+            }
+        };
+        let lib_dir = ctx.husky_dir.join("lib");
+        let path = match (
+            fs::canonicalize(ctx.husky_dir.join(&include)),
+            fs::canonicalize(&lib_dir),
+        ) {
+            (Ok(path), Ok(lib_dir)) if path.starts_with(&lib_dir) && path.is_file() => path,
+            _ => return Err(Error::IncludeNotFound(src.to_owned(), include)),
+        };
+        if including.contains(&path) {
+            return Err(Error::RecursiveInclude(path));
+        }
+        including.push(path.clone());
+        let included = read_user_hook_lines(&path, ctx, including)?;
+        including.pop();
+        lines.push(line);
+        // Shebang of the included file is not necessary
+        let skip = matches!(included.first(), Some(l) if l.starts_with("#!"));
+        lines.extend(included.into_iter().skip(skip as usize));
+    }
+    Ok(lines)
 }
 
 #[cfg(target_os = "windows")]
-fn create_executable_file(path: &Path) -> io::Result<File> {
-    File::create(path)
+fn symlink_file(src: &Path, dst: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(src, dst)
 }
 
 #[cfg(not(target_os = "windows"))]
-fn create_executable_file(path: &Path) -> io::Result<File> {
-    use std::os::unix::fs::OpenOptionsExt;
-
-    fs::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .mode(0o755)
-        .open(path)
-}
-
-fn install_hook(hook: &str) -> Result<()> {
-    let hook_path = {
-        let mut p = resolve_gitdir()?;
-        p.push("hooks");
-        p.push(hook);
-        p
-    };
-    if !hook_already_exists(&hook_path) {
-        let mut f = create_executable_file(&hook_path)?;
-        write_script(&mut f)?;
+fn symlink_file(src: &Path, dst: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(src, dst)
+}
+
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .map(|md| md.file_type().is_symlink())
+        .unwrap_or(false)
+}
+
+fn command_exists(name: &str) -> bool {
+    match env::var_os("PATH") {
+        Some(paths) => env::split_paths(&paths).any(|dir| dir.join(name).is_file()),
+        None => false,
+    }
+}
+
+// Check syntax of shell script with `-n` option of the shell. The check is skipped when the shell
+// cannot be run.
+fn check_shell_syntax(shell: &str, lines: &[String]) -> Option<String> {
+    let mut child = Command::new(shell)
+        .arg("-n")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    // Shell may exit before reading all input
+    let _ = child.stdin.take()?.write_all(lines.join("\n").as_bytes());
+    let out = child.wait_with_output().ok()?;
+    if out.status.success() {
+        None
+    } else {
+        Some(String::from_utf8_lossy(&out.stderr).trim().to_string())
     }
-    Ok(())
 }
 
-fn install_user_hook(src: &Path, dst: &Path) -> Result<()> {
-    if hook_already_exists(dst) {
+// Check the user hook script before installing it so that broken hooks are not installed
+fn validate_user_hook(src: &Path, lines: &[String]) -> Result<()> {
+    let invalid = |msg: String| Err(Error::InvalidUserHook(src.to_owned(), msg));
+
+    // Windows scripts are run by their interpreters on Windows
+    if windows_script_extension(src).is_some() {
+        return Ok(());
+    }
+    // Git for Windows runs hooks with its own shell and interpreters
+    if cfg!(target_os = "windows") {
         return Ok(());
     }
 
-    let mut lines = {
-        let mut vec = vec![];
-        for line in io::BufReader::new(File::open(src)?).lines() {
-            vec.push(line?);
+    let shebang = lines[0]
+        .strip_prefix("#!")
+        .map(|l| l.split_whitespace().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let shell = match shebang.first() {
+        // Git runs the script without shebang with sh
+        None => "sh",
+        Some(env) if env.ends_with("/env") => {
+            let name = match shebang[1..].iter().find(|w| !w.starts_with('-')) {
+                Some(name) => *name,
+                None => return invalid(format!("no command is given to '{}' in shebang", env)),
+            };
+            if !command_exists(name) {
+                return invalid(format!("interpreter '{}' in shebang is not found", name));
+            }
+            name
+        }
+        Some(interp) => {
+            if !Path::new(interp).is_file() {
+                return invalid(format!("interpreter '{}' in shebang is not found", interp));
+            }
+            interp
         }
-        vec
     };
 
+    let name = Path::new(shell).file_name().unwrap_or_default();
+    if ["sh", "bash", "dash", "ksh", "zsh"]
+        .iter()
+        .any(|s| name == *s)
+    {
+        if let Some(msg) = check_shell_syntax(shell, lines) {
+            return invalid(format!("syntax error: {}", msg));
+        }
+    }
+    Ok(())
+}
+
+fn install_user_hook(src: &Path, dst_file_path: &Path, ctx: &UserHookContext) -> Result<bool> {
+    let src = &fs::canonicalize(src)?;
+    let mut lines = read_user_hook_lines(src, ctx, &mut vec![])?;
+
+    // Script is linked only when it does not need to be modified with template variables, included
+    // files or CRLF line endings (symlink-user-hooks feature)
+    let source = fs::read_to_string(src)?;
+    let link = cfg!(feature = "symlink-user-hooks")
+        && !source.contains('\r')
+        && source.lines().eq(lines.iter().map(String::as_str));
+
     if lines.is_empty() {
         return Err(Error::EmptyUserHook(src.to_owned()));
     }
+    validate_user_hook(src, &lines)?;
 
     // Insert cargo-husky package version information as comment
     if !lines[0].starts_with("#!") {
@@ -226,23 +606,120 @@ fn install_user_hook(src: &Path, dst: &Path) -> Result<()> {
             env!("CARGO_PKG_HOMEPAGE")
         ),
     );
+    let mut content = lines.join("\n");
+    content.push('\n');
 
-    let dst_file_path = dst.join(src.file_name().unwrap());
+    debug!(
+        "User hook {:?} to {:?} ({})",
+        src,
+        dst_file_path,
+        if link { "link" } else { "copy" }
+    );
+    if is_symlink(dst_file_path) {
+        match fs::read_link(dst_file_path) {
+            Ok(ref target) if target == src => {}
+            // Someone else put the symlink
+            _ => {
+                return hook_not_installed(Error::ForeignHook(dst_file_path.to_owned()))
+                    .map(|_| false);
+            }
+        }
+        if link {
+            return keep(dst_file_path);
+        }
+        remove(dst_file_path)?;
+    } else {
+        match hook_state(dst_file_path) {
+            HookState::Foreign => {
+                return hook_not_installed(Error::ForeignHook(dst_file_path.to_owned()))
+                    .map(|_| false);
+            }
+            HookState::Newer => return keep_newer(dst_file_path),
+            // The script is installed again when its source was changed
+            HookState::UpToDate if !link => {
+                if is_same_content(dst_file_path, &content, ctx.mode) {
+                    return keep(dst_file_path);
+                }
+            }
+            HookState::UpToDate => remove(dst_file_path)?,
+            HookState::Outdated => {
+                backup(dst_file_path)?;
+                if link {
+                    remove(dst_file_path)?;
+                }
+            }
+            HookState::Missing => {}
+        }
+    }
 
-    let mut f = io::BufWriter::new(create_executable_file(&dst_file_path)?);
-    for line in lines {
-        writeln!(f, "{}", line)?;
+    // Fall back to copying the script when symlink is not available on the file system
+    if link && dry_run() {
+        would("link", dst_file_path);
+        return Ok(true);
+    }
+    if link && symlink_file(src, dst_file_path).is_ok() {
+        return Ok(true);
     }
 
-    Ok(())
+    write_hook(dst_file_path, &content, ctx.mode)?;
+    Ok(true)
+}
+
+// Extensions of scripts which are recognized as user hooks without executable bit
+const SCRIPT_EXTENSIONS: &[&str] = &["sh", "bash", "ps1", "cmd", "bat"];
+
+// Extensions of Windows scripts which Git can't run directly. They are run by the generated sh
+// wrapper script (see `install_platform_user_hook`)
+const WINDOWS_SCRIPT_EXTENSIONS: &[&str] = &["ps1", "cmd", "bat"];
+
+fn windows_script_extension(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    WINDOWS_SCRIPT_EXTENSIONS
+        .iter()
+        .find(|e| **e == ext)
+        .copied()
+}
+
+// Platforms in names of user hook scripts such as `pre-commit.unix` (see `platform_priority`)
+const PLATFORMS: &[&str] = &[
+    "unix",
+    "windows",
+    "linux",
+    "macos",
+    "freebsd",
+    "netbsd",
+    "openbsd",
+    "dragonfly",
+    "solaris",
+    "illumos",
+];
+
+// Executable bit is meaningless on Windows and in repositories with `core.fileMode=false` (e.g. on
+// NTFS mounted in WSL). There a user hook script is recognized by its shebang or its name instead.
+// Names without extension like Git hooks, or only with extensions of scripts and platforms are
+// accepted so that files such as `README.md` are not installed.
+fn is_script_file(entry: &fs::DirEntry) -> bool {
+    if !entry.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
+        return false;
+    }
+    let name = entry.file_name().to_string_lossy().to_lowercase();
+    if name
+        .split('.')
+        .skip(1)
+        .all(|part| SCRIPT_EXTENSIONS.contains(&part) || PLATFORMS.contains(&part))
+    {
+        return true;
+    }
+    let mut head = [0; 2];
+    File::open(entry.path())
+        .and_then(|mut f| f.read_exact(&mut head))
+        .map(|_| &head == b"#!")
+        .unwrap_or(false)
 }
 
 #[cfg(target_os = "windows")]
 fn is_executable_file(entry: &fs::DirEntry) -> bool {
-    match entry.file_type() {
-        Ok(ft) => ft.is_file(),
-        Err(..) => false,
-    }
+    is_script_file(entry)
 }
 
 #[cfg(not(target_os = "windows"))]
@@ -264,62 +741,952 @@ fn is_executable_file(entry: &fs::DirEntry) -> bool {
     mode & 0o555 == 0o555 // Check file is read and executable mode
 }
 
-fn install_user_hooks() -> Result<()> {
-    let git_dir = resolve_gitdir()?;
-    let user_hooks_dir = {
-        let mut p = git_dir.clone();
-        p.pop();
-        p.push(".cargo-husky");
-        p.push("hooks");
-        p
+// Directory such as `pre-commit.d` which contains scripts of the hook
+fn is_hook_dir(entry: &fs::DirEntry) -> bool {
+    entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false)
+        && entry.file_name().to_string_lossy().ends_with(".d")
+}
+
+// Scripts in the directory which can be installed as user hooks. `file_mode` is `core.fileMode`
+fn executable_files(dir: &Path, file_mode: bool) -> Result<Vec<PathBuf>> {
+    let is_hook = if file_mode {
+        is_executable_file
+    } else {
+        is_script_file
     };
+    Ok(fs::read_dir(dir)?
+        .filter_map(|e| e.ok().filter(is_hook).map(|e| e.path()))
+        .collect())
+}
 
+// Install scripts in `<hook>.d` directory and the hook script which runs them
+fn install_user_hook_dir(
+    src: &Path,
+    hooks_dir: &Path,
+    generated: &[&str],
+    ctx: &UserHookContext,
+) -> Result<()> {
+    let dir_name = src.file_name().unwrap();
+    let scripts = executable_files(src, ctx.file_mode)?;
+    if scripts.is_empty() {
+        return Err(Error::InvalidUserHooksDir(src.to_owned()));
+    }
+    let dst = hooks_dir.join(dir_name);
+    if dry_run() {
+        if !dst.is_dir() {
+            would("create directory", &dst);
+        }
+    } else {
+        fs::create_dir_all(&dst)?;
+    }
+    let mut installed = vec![];
+    for script in &scripts {
+        let file_name = script.file_name().unwrap();
+        if install_user_hook(script, &dst.join(file_name), ctx)? {
+            installed.push(file_name.to_string_lossy().into_owned());
+        }
+    }
+    // Remove scripts which were installed by cargo-husky but were removed from the directory
+    let src_dir = fs::canonicalize(src)?;
+    let installed_scripts = if dst.is_dir() {
+        fs::read_dir(&dst)?.collect::<io::Result<Vec<_>>>()?
+    } else {
+        vec![]
+    };
+    for entry in installed_scripts {
+        let path = entry.path();
+        if src.join(path.file_name().unwrap()).exists() {
+            continue;
+        }
+        if is_symlink(&path) {
+            if fs::read_link(&path)?.starts_with(&src_dir) {
+                remove(&path)?;
+            }
+        } else if let HookState::UpToDate | HookState::Outdated = hook_state(&path) {
+            remove(&path)?;
+        }
+    }
+
+    let dir_name = dir_name.to_string_lossy();
+    let hook = dir_name.trim_end_matches(".d");
+    install_generated_hook(
+        &hooks_dir.join(user_hook_name(hook, generated)),
+        &script::render_dispatcher(hook, &ctx.config)?,
+        ctx.mode,
+    )?;
+    if !installed.is_empty() {
+        installed.sort();
+        report_installed(&format!("user hook {}", dir_name), &installed);
+    }
+    Ok(())
+}
+
+// Put the hook script generated for user hooks
+fn install_generated_hook(hook_path: &Path, script: &str, mode: u32) -> Result<()> {
+    match hook_state(hook_path) {
+        HookState::UpToDate if is_same_content(hook_path, script, mode) => return keep(hook_path),
+        HookState::Foreign => return hook_not_installed(Error::ForeignHook(hook_path.to_owned())),
+        HookState::Newer => return keep_newer(hook_path),
+        HookState::UpToDate | HookState::Outdated => backup(hook_path)?,
+        HookState::Missing => {}
+    }
+    write_hook(hook_path, script, mode)
+}
+
+// Hook name and priority of the user hook script. A script can be put for specific platform such
+// as `pre-commit.unix`, `pre-commit.macos` or `pre-commit.windows.ps1`. More specific variant has
+// higher priority. Windows script such as `pre-commit.ps1` is for Windows as well as
+// `pre-commit.windows.ps1`. `None` means the script is for other platforms.
+fn platform_priority(file_name: &str) -> Option<(&str, u8)> {
+    let mut parts = file_name.splitn(3, '.');
+    let hook = parts.next()?;
+    let priority = match parts.next() {
+        None => 0,
+        Some(ext) if WINDOWS_SCRIPT_EXTENSIONS.contains(&ext.to_lowercase().as_str()) => {
+            if !cfg!(windows) || parts.next().is_some() {
+                return None;
+            }
+            1
+        }
+        Some("unix") if cfg!(unix) => 1,
+        Some("windows") if cfg!(windows) => 1,
+        Some(os) if os == env::consts::OS => 2,
+        Some(_) => return None,
+    };
+    Some((hook, priority))
+}
+
+// Install the user hook script selected for the platform. Windows script such as PowerShell script
+// is installed with its extension and run by sh wrapper script with the hook name since Git runs
+// hooks with sh.
+fn install_platform_user_hook(
+    src: &Path,
+    hook: &str,
+    hooks_dir: &Path,
+    generated: &[&str],
+    ctx: &UserHookContext,
+) -> Result<()> {
+    let name = user_hook_name(hook, generated);
+    let installed = match windows_script_extension(src) {
+        None => install_user_hook(src, &hooks_dir.join(&name), ctx)?,
+        Some(ext) => {
+            let script = hooks_dir.join(format!("{}.{}", name, ext));
+            let installed = install_user_hook(src, &script, ctx)?;
+            install_generated_hook(
+                &hooks_dir.join(&name),
+                &script::render_windows_runner(&name, ext, &ctx.config)?,
+                ctx.mode,
+            )?;
+            installed
+        }
+    };
+    if installed {
+        let file_name = src.file_name().unwrap().to_string_lossy().into_owned();
+        report_installed(&format!("user hook {}", name), &[file_name]);
+    }
+    Ok(())
+}
+
+// File name of the user hook script. When the hook is also generated by cargo-husky
+// (combine-hooks feature), the user hook is installed as `{hook}.user` and run by the generated hook.
+fn user_hook_name(hook: &str, generated: &[&str]) -> String {
+    if generated.contains(&hook) {
+        format!("{}.user", hook)
+    } else {
+        hook.to_string()
+    }
+}
+
+// Install user hooks and return names of the installed hooks
+fn install_user_hooks(generated: &[&str], hooks_dir: &Path) -> Result<Vec<String>> {
+    let git_dir = resolve_gitdir()?;
+    let user_hooks_dir = resolve_work_tree(&git_dir)
+        .join(".cargo-husky")
+        .join("hooks");
+
+    debug!("User hooks directory: {:?}", user_hooks_dir);
     if !user_hooks_dir.is_dir() {
         return Err(Error::InvalidUserHooksDir(user_hooks_dir));
     }
+    // Install user hooks again when scripts or included files are changed
+    println!(
+        "cargo:rerun-if-changed={}",
+        user_hooks_dir.parent().unwrap().display()
+    );
 
-    let hook_paths = fs::read_dir(&user_hooks_dir)?
-        .filter_map(|e| e.ok().filter(is_executable_file).map(|e| e.path()))
+    let file_mode = file_mode_enabled(&git_dir);
+    debug!("core.fileMode: {}", file_mode);
+    let hook_paths = executable_files(&user_hooks_dir, file_mode)?;
+    let hook_dirs = fs::read_dir(&user_hooks_dir)?
+        .filter_map(|e| e.ok().filter(is_hook_dir).map(|e| e.path()))
         .collect::<Vec<_>>();
 
-    if hook_paths.is_empty() {
+    if hook_paths.is_empty() && hook_dirs.is_empty() {
         return Err(Error::InvalidUserHooksDir(user_hooks_dir));
     }
 
-    let hooks_dir = git_dir.join("hooks");
+    // Select the script of each hook for the platform
+    let mut selected: BTreeMap<String, (u8, PathBuf)> = BTreeMap::new();
     for path in hook_paths {
-        install_user_hook(&path, &hooks_dir)?;
+        let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let (hook, priority) = match platform_priority(&file_name) {
+            Some(p) => p,
+            None => continue,
+        };
+        let preferred = match selected.get(hook) {
+            Some((p, _)) => *p < priority,
+            None => true,
+        };
+        if preferred {
+            selected.insert(hook.to_string(), (priority, path));
+        }
+    }
+
+    let config = load_config(&git_dir)?;
+    let ctx = UserHookContext {
+        vars: template_vars(&config, &git_dir)?,
+        husky_dir: user_hooks_dir.parent().unwrap().to_owned(),
+        file_mode,
+        mode: hook_mode(&config)?,
+        config,
+    };
+    let mut combined = vec![];
+    for (hook, (_, path)) in selected {
+        let dir_name = format!("{}.d", hook);
+        if hook_dirs.iter().any(|d| d.ends_with(&dir_name)) {
+            return Err(Error::ConflictingUserHook(path));
+        }
+        install_platform_user_hook(&path, &hook, hooks_dir, generated, &ctx)?;
+        combined.push(hook);
+    }
+    for dir in hook_dirs {
+        install_user_hook_dir(&dir, hooks_dir, generated, &ctx)?;
+        let dir_name = dir.file_name().unwrap().to_string_lossy();
+        combined.push(dir_name.trim_end_matches(".d").to_string());
+    }
+    Ok(combined)
+}
+
+// Lock file older than this is regarded as left by an interrupted build
+const STALE_LOCK: Duration = Duration::from_secs(30);
+
+// Advisory lock to serialize installations by build scripts running concurrently in the same
+// repository (e.g. `cargo test` for several workspace members). The lock file is removed on drop.
+struct InstallLock(PathBuf);
+
+// Owner and modified time identifying the lock file
+fn lock_identity(path: &Path) -> io::Result<(String, SystemTime)> {
+    let modified = fs::metadata(path)?.modified()?;
+    Ok((fs::read_to_string(path)?, modified))
+}
+
+// Remove the lock file left by an interrupted build. Other builds may be waiting for the same lock,
+// so it is renamed first and removed only when it is still the stale one. When another build had
+// already replaced it with its own lock, the lock is put back.
+fn remove_stale_lock(path: &Path, stale: &(String, SystemTime)) {
+    let mut name = path.file_name().unwrap().to_os_string();
+    name.push(format!(".{}.stale", process::id()));
+    let renamed = path.with_file_name(name);
+    if fs::rename(path, &renamed).is_err() {
+        return; // Another build removed it
+    }
+    if lock_identity(&renamed).ok().as_ref() != Some(stale) {
+        debug!("Lock {:?} was acquired by another build", path);
+        // Hard link does not overwrite a lock created in the meantime
+        let _ = fs::hard_link(&renamed, path);
+    }
+    let _ = fs::remove_file(&renamed);
+}
+
+impl InstallLock {
+    fn acquire(gitdir: &Path) -> Result<InstallLock> {
+        let path = gitdir.join("cargo-husky.lock");
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut f) => {
+                    if let Err(err) = writeln!(f, "{}", process::id()) {
+                        let _ = fs::remove_file(&path);
+                        return Err(err.into());
+                    }
+                    debug!("Acquired lock {:?}", path);
+                    return Ok(InstallLock(path));
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+            match lock_identity(&path) {
+                Ok(lock) if lock.1.elapsed().is_ok_and(|d| d > STALE_LOCK) => {
+                    debug!("Removing stale lock {:?}", path);
+                    remove_stale_lock(&path, &lock);
+                    continue;
+                }
+                _ => {}
+            }
+            debug!("Waiting for another build to release lock {:?}", path);
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+impl Drop for InstallLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
     }
+}
 
+// Hooks directories where hooks are installed. Worktrees share hooks in the common Git directory
+// unless `core.hooksPath` is set in their worktree config. With `worktree-hooks` feature, a linked
+// worktree gets its own hooks directory. With `all-worktrees` feature, hooks are installed in all
+// worktrees of the repository at once.
+fn install_hooks_dirs(gitdir: &Path) -> Result<Vec<PathBuf>> {
+    let gitdirs = if cfg!(feature = "all-worktrees") {
+        worktree_gitdirs(gitdir)
+    } else {
+        vec![gitdir.to_owned()]
+    };
+    let mut dirs = vec![];
+    for gitdir in gitdirs {
+        let dir = if cfg!(feature = "worktree-hooks") {
+            worktree_hooks_dir(&gitdir)?
+        } else {
+            hooks_dir(&gitdir)
+        };
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    Ok(dirs)
+}
+
+// Git directories of the main worktree and linked worktrees in `.git/worktrees`
+fn worktree_gitdirs(gitdir: &Path) -> Vec<PathBuf> {
+    let common = common_dir(gitdir);
+    let mut linked = fs::read_dir(common.join("worktrees"))
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.join("gitdir").is_file())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    linked.sort();
+    let mut dirs = vec![common];
+    dirs.extend(linked);
+    dirs
+}
+
+// Hooks directory of the linked worktree itself (`.git/worktrees/<name>/hooks`). `core.hooksPath` is
+// set in its `config.worktree` so that Git runs hooks there. It requires `extensions.worktreeConfig`.
+fn worktree_hooks_dir(gitdir: &Path) -> Result<PathBuf> {
+    let shared = common_dir(gitdir).join("hooks");
+    let current = hooks_dir(gitdir);
+    // The main worktree uses `.git/hooks`. A worktree which already configures its hooks is kept
+    if common_dir(gitdir) == gitdir || current != shared {
+        return Ok(current);
+    }
+    if !worktree_config_enabled(gitdir) {
+        println!(
+            "cargo:warning=cargo-husky: Hooks of worktree {:?} are shared with other worktrees since 'extensions.worktreeConfig' is not enabled. Run 'git config extensions.worktreeConfig true' to install hooks per worktree",
+            gitdir
+        );
+        return Ok(current);
+    }
+    let dir = gitdir.join("hooks");
+    let config = gitdir.join("config.worktree");
+    if dry_run() {
+        println!(
+            "cargo:warning=cargo-husky: Would set core.hooksPath to {:?} in {:?}",
+            dir, config
+        );
+        return Ok(dir);
+    }
+    debug!("Setting core.hooksPath to {:?} in {:?}", dir, config);
+    let out = Command::new("git")
+        .arg("config")
+        .arg("--file")
+        .arg(&config)
+        .arg("core.hooksPath")
+        .arg(&dir)
+        .output()?;
+    if !out.status.success() {
+        return Err(Error::Git(
+            String::from_utf8_lossy(&out.stderr).trim().to_string(),
+        ));
+    }
+    Ok(dir)
+}
+
+// `.git/hooks` is missing in repositories created with an empty template directory or in some
+// shallow clones. It is created here. A directory which is not writable (e.g. a read-only mount) is
+// reported as hooks not installed instead of an IO error in the middle of installation.
+fn prepare_hooks_dir(hooks_dir: &Path) -> Result<()> {
+    let not_writable = |err| Error::HooksDirNotWritable(hooks_dir.to_owned(), err);
+    let dir = match hooks_dir.parent() {
+        Some(parent) if !hooks_dir.is_dir() => parent,
+        _ => hooks_dir,
+    };
+    if fs::metadata(dir)?.permissions().readonly() {
+        let err = io::Error::new(io::ErrorKind::PermissionDenied, "permission denied");
+        return Err(not_writable(err));
+    }
+    if dry_run() {
+        if !hooks_dir.is_dir() {
+            would("create directory", hooks_dir);
+        }
+        return Ok(());
+    }
+    if !hooks_dir.is_dir() {
+        debug!("Creating hooks directory {:?}", hooks_dir);
+        fs::create_dir_all(hooks_dir).map_err(not_writable)?;
+    }
+    // Permission bits do not tell read-only file systems and ACLs. Try to write a file actually
+    let probe = hooks_dir.join(format!(".cargo-husky.{}.probe", process::id()));
+    File::create(&probe).map_err(not_writable)?;
+    fs::remove_file(&probe)?;
     Ok(())
 }
 
 fn install() -> Result<()> {
-    if cfg!(feature = "user-hooks") {
-        return install_user_hooks();
+    if cfg!(feature = "mercurial") && resolve_gitdir().is_err() {
+        if let Some(hgdir) = find_hgdir() {
+            return install_mercurial(&hgdir);
+        }
     }
-    if cfg!(feature = "prepush-hook") {
-        install_hook("pre-push")?;
+    let gitdir = resolve_gitdir()?;
+    let features = enabled_features();
+    debug!("Enabled features: {}", features.join(", "));
+    let problems = features::validate(&features);
+    if !problems.is_empty() {
+        return Err(Error::InvalidFeatures(problems));
     }
-    if cfg!(feature = "precommit-hook") {
-        install_hook("pre-commit")?;
+
+    // When `user-hooks` feature is enabled without `combine-hooks` feature, only user hooks are installed
+    let generated = if !cfg!(feature = "user-hooks") || cfg!(feature = "combine-hooks") {
+        generated_hooks()
+    } else {
+        vec![]
+    };
+    debug!("Generated hooks: {:?}", generated);
+    if cfg!(feature = "advise-only") {
+        return advise(&gitdir, &generated);
     }
-    if cfg!(feature = "postmerge-hook") {
-        install_hook("post-merge")?;
+
+    // Worktrees share hooks in the common Git directory
+    let _lock = if dry_run() {
+        None
+    } else {
+        Some(InstallLock::acquire(&common_dir(&gitdir))?)
+    };
+    let hooks_dirs = install_hooks_dirs(&gitdir)?;
+    debug!("Hooks directories: {:?}", hooks_dirs);
+    // The managed block of npm husky runs hooks in the common Git directory
+    let common_hooks_dir = common_dir(&gitdir).join("hooks");
+    let npm_husky = npm_husky_dir(&gitdir).filter(|_| hooks_dirs.contains(&common_hooks_dir));
+    let config = load_config(&gitdir)?;
+    let mut user_hooks = vec![];
+    for hooks_dir in &hooks_dirs {
+        prepare_hooks_dir(hooks_dir)?;
+        user_hooks = if cfg!(feature = "user-hooks") {
+            install_user_hooks(&generated, hooks_dir)?
+        } else {
+            vec![]
+        };
+        let npm_husky = npm_husky
+            .as_deref()
+            .filter(|_| *hooks_dir == common_hooks_dir);
+        remove_stale_hooks(hooks_dir, &generated, npm_husky)?;
+        if !generated.is_empty() {
+            for hook in &generated {
+                let user = user_hooks.iter().any(|h| h == hook);
+                install_hook(hook, hooks_dir, &gitdir, &config, user)?;
+            }
+        }
+        // `cargo husky verify` compares the hooks with the record
+        let record = record_path(hooks_dir);
+        if dry_run() {
+            would("record installed hooks in", &record);
+        } else {
+            InstallRecord::scan(hooks_dir, &config, &generated, &user_hooks)?
+                .save(&record)
+                .or_else(hook_not_installed)?;
+        }
+    }
+    if let Some(dir) = npm_husky {
+        let mut hooks = generated
+            .iter()
+            .map(|h| h.to_string())
+            .chain(user_hooks)
+            .collect::<Vec<_>>();
+        hooks.sort();
+        hooks.dedup();
+        for hook in hooks {
+            install_npm_husky_block(&dir, &hook, &config)?;
+        }
     }
     Ok(())
 }
 
+// Generate hooks in OUT_DIR instead of `.git` and tell how to install them (advise-only feature).
+// Nothing is written to `.git` so that the hooks can be reviewed before installing them by hand or
+// by `cargo husky install`.
+fn advise(gitdir: &Path, generated: &[&str]) -> Result<()> {
+    let config = load_config(gitdir)?;
+    let mode = hook_mode(&config)?;
+    let dir = PathBuf::from(env::var("OUT_DIR")?).join("hooks");
+    // Hooks no longer enabled are not left
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+    for hook in generated {
+        let script = normalize_line_endings(&script::render(hook, false, false, &config)?);
+        write_executable_file(&dir.join(hook), script.as_bytes(), mode)?;
+        if cfg!(feature = "native-runner") {
+            script::plan(hook, &config)?.save(&plan_path(&dir, hook), hook)?;
+        }
+    }
+    InstallRecord::scan(&dir, &config, generated, &[])?.save(&record_path(&dir))?;
+
+    // cargo shows only the first line of each warning
+    let hooks_dir = hooks_dir(gitdir);
+    println!(
+        "cargo:warning=cargo-husky: Hooks were generated in {:?} but not installed (advise-only feature). Install them by 'cargo husky install {}'",
+        dir,
+        script::quote(&dir.to_string_lossy())
+    );
+    for hook in generated {
+        let steps = script::steps(hook, &config)?;
+        let mut what = hook.to_string();
+        if !steps.is_empty() {
+            let details = steps.iter().map(command_summary).collect::<Vec<_>>();
+            what += &format!(" ({})", details.join(", "));
+        }
+        let src = script::quote(&dir.join(hook).to_string_lossy());
+        let dst = script::quote(&hooks_dir.join(hook).to_string_lossy());
+        println!(
+            "cargo:warning=cargo-husky: {} can be installed by hand: cp {} {} && chmod {:o} {}",
+            what, src, dst, mode, dst
+        );
+    }
+    Ok(())
+}
+
+// Directory of npm husky when `core.hooksPath` points to it (`.husky` until husky v8 and `.husky/_`
+// since husky v9). Git runs hook scripts in `core.hooksPath` instead of `.git/hooks`.
+fn npm_husky_dir(gitdir: &Path) -> Option<PathBuf> {
+    let root = resolve_work_tree(gitdir);
+    let out = Command::new("git")
+        .args(["config", "--get", "core.hooksPath"])
+        .current_dir(&root)
+        .output()
+        .ok()?;
+    let hooks_path = path_from_bytes(out.stdout.trim_ascii());
+    if !out.status.success() || hooks_path.as_os_str().is_empty() {
+        return None;
+    }
+    let dir = root.join(".husky");
+    if !root.join(&hooks_path).starts_with(&dir) || !dir.is_dir() {
+        debug!(
+            "core.hooksPath {:?} is not a directory of npm husky",
+            hooks_path
+        );
+        return None;
+    }
+    debug!("Found npm husky directory {:?}", dir);
+    Some(dir)
+}
+
+// Put the managed block which runs the hook in `.git/hooks` into the hook script of npm husky
+// The script of npm husky keeps its permissions
+fn install_npm_husky_block(husky_dir: &Path, hook: &str, config: &Config) -> Result<()> {
+    let path = husky_dir.join(hook);
+    let existing = fs::read_to_string(&path).ok();
+    let script = put_managed_block(
+        existing.as_deref(),
+        &script::render_managed_block(hook),
+        config,
+    )?;
+    if existing.as_deref() == Some(script.as_str()) {
+        return keep(&path);
+    }
+    let mode = match permission_bits(&path) {
+        Some(mode) => mode,
+        None => hook_mode(config)?,
+    };
+    write_hook(&path, &script, mode)?;
+    report_installed(&format!("npm husky {}", hook), &[]);
+    Ok(())
+}
+
+// Remove the managed block from the hook script of npm husky or another hook manager. The script is
+// removed when nothing remains but the shebang.
+fn remove_block(path: &Path) -> Result<()> {
+    let script = match fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(_) => return Ok(()),
+    };
+    match remove_managed_block(&script) {
+        Some(rest)
+            if rest
+                .lines()
+                .all(|l| l.trim().is_empty() || l.starts_with("#!")) =>
+        {
+            remove(path)
+        }
+        Some(rest) => write_hook(
+            path,
+            &rest,
+            permission_bits(path).unwrap_or(DEFAULT_HOOK_MODE),
+        ),
+        None => Ok(()),
+    }
+}
+
+// Remove hooks generated for features which were disabled. The hook chained by the removed hook is
+// put back in place. The managed blocks in hook scripts of npm husky and other hook managers are also
+// removed.
+fn remove_stale_hooks(
+    hooks_dir: &Path,
+    generated: &[&str],
+    npm_husky: Option<&Path>,
+) -> Result<()> {
+    for hook in stale_hooks(hooks_dir, generated) {
+        let path = hooks_dir.join(hook);
+        debug!("Removing stale hook {:?}", path);
+        let chained = chained_hook_path(&path);
+        let merged = merged_hook_path(&path);
+        if is_generated_hook(&merged) {
+            remove(&merged)?;
+            remove_block(&path)?;
+        } else if chained.is_file() {
+            rename(&chained, &path)?;
+        } else {
+            remove(&path)?;
+        }
+        let plan = plan_path(hooks_dir, hook);
+        if plan.is_file() {
+            remove(&plan)?;
+        }
+        if let Some(dir) = npm_husky {
+            remove_block(&dir.join(hook))?;
+        }
+        if !dry_run() {
+            println!(
+                "cargo:warning=cargo-husky: Removed {} (no longer enabled)",
+                hook
+            );
+        }
+    }
+    Ok(())
+}
+
+// Find `.hg` directory of a Mercurial repository. It is only used when `.git` directory is not found.
+fn find_hgdir() -> Option<PathBuf> {
+    let dir = env::var("OUT_DIR").ok()?;
+    let hgdir = Path::new(&dir)
+        .ancestors()
+        .map(|d| d.join(".hg"))
+        .find(|d| d.is_dir())?;
+    debug!("Found .hg directory {:?}", hgdir);
+    Some(hgdir)
+}
+
+// Mercurial hook equivalent to the Git hook
+fn mercurial_hook(hook: &str) -> Option<&'static str> {
+    match hook {
+        "pre-commit" => Some("precommit"),
+        "pre-push" => Some("pre-push"),
+        "post-commit" => Some("commit"),
+        _ => None,
+    }
+}
+
+// Write hook scripts to `.hg/cargo-husky` and register them in the managed section of `.hg/hgrc`.
+// Other sections of `.hg/hgrc` are not touched.
+fn install_mercurial(hgdir: &Path) -> Result<()> {
+    let _lock = if dry_run() {
+        None
+    } else {
+        Some(InstallLock::acquire(hgdir)?)
+    };
+    let config = load_config(hgdir)?;
+    let mode = hook_mode(&config)?;
+    let scripts_dir = hgdir.join("cargo-husky");
+    let mut entries = String::new();
+    for hook in generated_hooks() {
+        let name = match mercurial_hook(hook) {
+            Some(name) => name,
+            None => {
+                debug!("{} hook has no equivalent in Mercurial", hook);
+                continue;
+            }
+        };
+        let path = scripts_dir.join(hook);
+        let script = script::render_mercurial(hook, &config)?;
+        if is_same_content(&path, &script, mode) {
+            keep::<()>(&path)?;
+        } else {
+            if !dry_run() {
+                fs::create_dir_all(&scripts_dir)?;
+            }
+            write_hook(&path, &script, mode)?;
+        }
+        let path_str = match path.to_str() {
+            Some(path) => path,
+            None => return hook_not_installed(Error::NonUtf8Path(path)),
+        };
+        entries += &format!("{}.cargo-husky = {}\n", name, script::quote(path_str));
+        let steps = script::steps(hook, &config)?;
+        report_installed(
+            &format!("Mercurial {} hook", name),
+            &steps.iter().map(command_summary).collect::<Vec<_>>(),
+        );
+    }
+
+    let hgrc = hgdir.join("hgrc");
+    let existing = fs::read_to_string(&hgrc).unwrap_or_default();
+    let block = format!(
+        "{}\n[hooks]\n{}{}\n",
+        MANAGED_BLOCK_BEGIN, entries, MANAGED_BLOCK_END
+    );
+    let content = put_managed_block(Some(&existing), &block, &config)?;
+    if content == existing {
+        return Ok(());
+    }
+    if dry_run() {
+        would("write", &hgrc);
+    } else {
+        fs::write(&hgrc, content)?;
+    }
+    Ok(())
+}
+
+// Report the hook which could not be installed. It fails the build with `strict-install` feature,
+// and it is shown as a warning of cargo with `warn-install` feature.
+fn hook_not_installed(err: Error) -> Result<()> {
+    debug!("Hook was not installed: {}", err);
+    if dry_run() {
+        println!("cargo:warning=cargo-husky: Would skip: {}", err);
+        return Ok(());
+    }
+    if cfg!(feature = "strict-install") {
+        return Err(err);
+    }
+    if cfg!(feature = "warn-install") {
+        println!("cargo:warning=cargo-husky: {}", err);
+    } else {
+        eprintln!("Warning: {:?}", err);
+    }
+    Ok(())
+}
+
+// Export commands of the generated hooks as configuration of the pre-commit framework instead of
+// installing hooks. `.pre-commit-config.yaml` is written unless it was written by someone else. In
+// the case, the configuration is written to `.pre-commit-config.cargo-husky.yaml` to be merged by hand.
+fn export(format: &str) -> Result<()> {
+    if format != "pre-commit" {
+        return Err(Error::UnknownExportFormat(format.to_string()));
+    }
+    let gitdir = resolve_gitdir()?;
+    let config = load_config(&gitdir)?;
+    let mut hooks = vec![];
+    for hook in generated_hooks() {
+        hooks.push((hook, script::steps(hook, &config)?));
+    }
+    let root = resolve_work_tree(&gitdir);
+    let mut path = root.join(".pre-commit-config.yaml");
+    let generated = fs::read_to_string(&path)
+        .map(|s| s.starts_with("# Generated by cargo-husky "))
+        .unwrap_or(true);
+    if !generated {
+        path = root.join(".pre-commit-config.cargo-husky.yaml");
+    }
+    debug!("Exporting commands to {:?}", path);
+    if dry_run() {
+        would("write", &path);
+    } else {
+        fs::write(&path, render_pre_commit_config(&hooks))?;
+    }
+    println!(
+        "cargo:warning=cargo-husky: Exported commands of hooks to {:?}",
+        path
+    );
+    Ok(())
+}
+
+// Environment variables set by CI services
+const CI_ENV_VARS: &[&str] = &[
+    "CI",
+    "CONTINUOUS_INTEGRATION",
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "TRAVIS",
+    "APPVEYOR",
+    "CIRCLECI",
+    "BUILDKITE",
+    "JENKINS_URL",
+    "TF_BUILD",
+    "TEAMCITY_VERSION",
+    "BITBUCKET_BUILD_NUMBER",
+    "DRONE",
+];
+
+// Hooks are not used on CI. Read-only checkouts may even fail to install them.
+fn detect_ci() -> Option<&'static str> {
+    CI_ENV_VARS
+        .iter()
+        .cloned()
+        .find(|name| match env::var(name) {
+            Ok(v) => !matches!(v.as_str(), "" | "0" | "false"),
+            Err(_) => false,
+        })
+}
+
+// Environment variables set while building packages of distributions such as Debian, Nix or Fedora
+const SANDBOX_ENV_VARS: &[&str] = &["NIX_BUILD_TOP", "SOURCE_DATE_EPOCH", "RPM_BUILD_ROOT"];
+
+// Writing files outside the build tree violates the policy of package builds. `.git` directory
+// which is not writable is reported on installing hooks.
+fn detect_sandbox() -> Option<String> {
+    SANDBOX_ENV_VARS
+        .iter()
+        .find(|name| var_os(name).is_some())
+        .map(|name| format!("Found '${}' in env", name))
+}
+
+// Repository listed in `safe.directory` of Git config is trusted even if it is owned by another user
+fn is_safe_directory(repo: &Path) -> bool {
+    let out = match Command::new("git")
+        .args(["config", "--get-all", "safe.directory"])
+        .output()
+    {
+        Ok(out) => out,
+        Err(_) => return false,
+    };
+    let repo = fs::canonicalize(repo).unwrap_or_else(|_| repo.to_owned());
+    out.stdout
+        .split(|b| *b == b'\n')
+        .any(|dir| dir == b"*" || fs::canonicalize(path_from_bytes(dir)).is_ok_and(|d| d == repo))
+}
+
+// Developers can disable installing hooks in all projects or in some repositories by their user
+// config (see `UserConfig`). The reason is returned when hooks must not be installed.
+fn denied_by_user_config() -> Result<Option<String>> {
+    let path = match user_config_path() {
+        Some(path) if path.is_file() => path,
+        _ => return Ok(None),
+    };
+    // Rerunning on a missing file would run the build script on every build
+    println!("cargo:rerun-if-changed={}", path.display());
+    let config = UserConfig::load(&path)?;
+    debug!("User config {:?}: {:?}", path, config);
+    // The repository is not found here. It is reported by installation
+    let gitdir = match resolve_gitdir() {
+        Ok(gitdir) => gitdir,
+        Err(_) => return Ok(None),
+    };
+    let root = resolve_work_tree(&gitdir);
+    let root = fs::canonicalize(&root).unwrap_or(root);
+    let remotes = remote_urls(&root);
+    Ok(config
+        .deny_reason(&root, &remotes)
+        .map(|reason| format!("{} in {:?}", reason, path)))
+}
+
+#[cfg(target_os = "windows")]
+fn check_owner() -> Result<()> {
+    Ok(())
+}
+
+// Like `safe.directory` of Git, hooks are not installed in a repository owned by another user to
+// avoid surprising writes into shared or mounted repositories. OUT_DIR is owned by the current user
+// since cargo created it.
+#[cfg(not(target_os = "windows"))]
+fn check_owner() -> Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let gitdir = match resolve_gitdir() {
+        Ok(dir) => dir,
+        Err(_) => return Ok(()), // Reported on installing hooks
+    };
+    let uid = fs::metadata(env::var("OUT_DIR")?)?.uid();
+    let owner = fs::metadata(&gitdir)?.uid();
+    debug!(
+        "Owner of {:?} is {} and current user is {}",
+        gitdir, owner, uid
+    );
+    if owner == uid || is_safe_directory(&resolve_work_tree(&gitdir)) {
+        return Ok(());
+    }
+    Err(Error::NotOwner(gitdir))
+}
+
 fn main() -> Result<()> {
+    // Hooks are installed when building again without dry run
+    println!("cargo:rerun-if-env-changed=CARGO_HUSKY_DRY_RUN");
+    println!("cargo:rerun-if-env-changed=CARGO_HUSKY_LOG");
+    println!("cargo:rerun-if-env-changed=CARGO_HUSKY_EXPORT");
+    // Git exports `$GIT_DIR` to hooks. They are tracked only when pointing to another repository so
+    // that cargo run by hooks does not rerun this script and rebuild everything each time
+    if env_overrides_discovery(Path::new(&var_os("OUT_DIR").unwrap_or_default())) {
+        println!("cargo:rerun-if-env-changed=GIT_DIR");
+        println!("cargo:rerun-if-env-changed=GIT_WORK_TREE");
+    }
+    println!("cargo:rerun-if-env-changed=XDG_CONFIG_HOME");
+    for name in CI_ENV_VARS.iter().chain(SANDBOX_ENV_VARS) {
+        println!("cargo:rerun-if-env-changed={}", name);
+    }
     if var_os("CARGO_HUSKY_DONT_INSTALL_HOOKS").is_some() {
         eprintln!("Warning: Found '$CARGO_HUSKY_DONT_INSTALL_HOOKS' in env, not doing anything!");
         return Ok(());
     }
+    if let Some(format) = var_os("CARGO_HUSKY_EXPORT") {
+        return export(&format.to_string_lossy());
+    }
+    if !cfg!(feature = "install-on-ci") {
+        if let Some(name) = detect_ci() {
+            eprintln!(
+                "Info: Found '${}' in env. Hooks are not installed on CI. Enable 'install-on-ci' feature to install them",
+                name
+            );
+            return Ok(());
+        }
+    }
+    if let Some(reason) = detect_sandbox() {
+        eprintln!(
+            "Info: {}. Hooks are not installed in sandboxed package builds",
+            reason
+        );
+        return Ok(());
+    }
+    match denied_by_user_config() {
+        Ok(Some(reason)) => {
+            eprintln!("Info: Hooks are not installed since {}", reason);
+            return Ok(());
+        }
+        Ok(None) => {}
+        Err(err) => return hook_not_installed(err),
+    }
+    if let Err(err) = check_owner() {
+        return hook_not_installed(err);
+    }
 
     match install() {
-        Err(e @ Error::GitDirNotFound) => {
-            // #2
-            eprintln!("Warning: {:?}", e);
+        Err(
+            e @ (Error::GitDirNotFound(_) | Error::InvalidGitFile(_) | Error::BareRepository(_)),
+        ) => hook_not_installed(e), // #2
+        Err(e @ Error::HooksDirNotWritable(..)) => hook_not_installed(e),
+        // Cargo.toml may use syntax which cargo accepts but the parser of cargo-husky does not
+        Err(e @ Error::Manifest(..)) => hook_not_installed(e),
+        Err(e) if cfg!(feature = "warn-install") && !cfg!(feature = "strict-install") => {
+            // cargo shows only the first line of a warning
+            for line in e.to_string().lines() {
+                println!("cargo:warning=cargo-husky: {}", line);
+            }
             Ok(())
         }
         otherwise => otherwise,
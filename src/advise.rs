@@ -0,0 +1,58 @@
+use backup::backup_hook;
+use error::{Error, Result};
+use hook::{is_managed_hook, permission_bits, write_executable_file, DEFAULT_HOOK_MODE};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Files directly in the directory sorted by their names
+fn files_in(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Install hook scripts which the build script generated in `dir` with advise-only feature into
+/// `hooks_dir`. Commands of the hooks (native-runner feature) and the record for `verify_hooks` are
+/// also installed. Hook scripts replaced by this function are backed up. Nothing is installed when
+/// some hook script was put by someone else. Names of the installed hooks are returned.
+pub fn install_advised_hooks(dir: &Path, hooks_dir: &Path) -> Result<Vec<String>> {
+    let scripts = files_in(dir)?;
+    for src in &scripts {
+        let dst = hooks_dir.join(src.file_name().unwrap());
+        if dst.is_file() && is_managed_hook(&dst)?.is_none() {
+            return Err(Error::ForeignHook(dst));
+        }
+    }
+
+    fs::create_dir_all(hooks_dir)?;
+    let mut installed = vec![];
+    for src in &scripts {
+        let name = src.file_name().unwrap();
+        let dst = hooks_dir.join(name);
+        let content = fs::read(src)?;
+        let mode = permission_bits(src);
+        if fs::read(&dst).ok().as_ref() != Some(&content) || permission_bits(&dst) != mode {
+            if dst.is_file() {
+                backup_hook(&dst)?;
+            }
+            write_executable_file(&dst, &content, mode.unwrap_or(DEFAULT_HOOK_MODE))?;
+        }
+        installed.push(name.to_string_lossy().into_owned());
+    }
+
+    let data = dir.join("cargo-husky");
+    if data.is_dir() {
+        let dst_dir = hooks_dir.join("cargo-husky");
+        fs::create_dir_all(&dst_dir)?;
+        for src in files_in(&data)? {
+            fs::copy(&src, dst_dir.join(src.file_name().unwrap()))?;
+        }
+    }
+    Ok(installed)
+}
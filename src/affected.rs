@@ -0,0 +1,169 @@
+use error::{Error, Result};
+use git;
+use json::{self, Value};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Workspace member read from `cargo metadata`
+struct Package {
+    name: String,
+    dir: PathBuf,
+    // Names of dependencies including dev and build dependencies
+    deps: Vec<String>,
+}
+
+fn canonicalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+fn parse_packages(metadata: &str) -> std::result::Result<(PathBuf, Vec<Package>), String> {
+    let root = json::parse(metadata)?;
+    let workspace_root = root
+        .get("workspace_root")
+        .and_then(Value::as_str)
+        .ok_or("'workspace_root' is missing")?;
+    let mut packages = vec![];
+    for pkg in root
+        .get("packages")
+        .and_then(Value::as_array)
+        .ok_or("'packages' is missing")?
+    {
+        let name = pkg
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or("'name' of package is missing")?;
+        let manifest = pkg
+            .get("manifest_path")
+            .and_then(Value::as_str)
+            .ok_or("'manifest_path' of package is missing")?;
+        let deps = pkg
+            .get("dependencies")
+            .and_then(Value::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|d| d.get("name").and_then(Value::as_str))
+            .map(String::from)
+            .collect();
+        packages.push(Package {
+            name: name.to_string(),
+            dir: canonicalize(
+                Path::new(manifest)
+                    .parent()
+                    .unwrap_or_else(|| Path::new("/")),
+            ),
+            deps,
+        });
+    }
+    Ok((canonicalize(Path::new(workspace_root)), packages))
+}
+
+// Files which affect all packages in the workspace
+fn affects_workspace(file: &Path, workspace_root: &Path) -> bool {
+    let name = file.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    matches!(
+        name,
+        "Cargo.lock" | "rust-toolchain" | "rust-toolchain.toml"
+    ) || file == workspace_root.join("Cargo.toml")
+        || file.components().any(|c| c.as_os_str() == ".cargo")
+}
+
+/// Names of workspace members affected by the changed files, in sorted order. `metadata` is output
+/// of `cargo metadata --format-version 1 --no-deps` and `files` are absolute paths of the changed
+/// files. Packages which depend on changed packages are also affected. `None` is returned when
+/// the changes affect the whole workspace (e.g. `Cargo.lock` is changed). Files outside workspace
+/// members are ignored.
+pub fn affected_packages(
+    metadata: &str,
+    files: &[PathBuf],
+) -> std::result::Result<Option<Vec<String>>, String> {
+    let (workspace_root, packages) = parse_packages(metadata)?;
+    let mut affected = BTreeSet::new();
+    for file in files {
+        if affects_workspace(file, &workspace_root) {
+            return Ok(None);
+        }
+        // Nested package is preferred to its parent directory's package
+        if let Some(pkg) = packages
+            .iter()
+            .filter(|p| file.starts_with(&p.dir))
+            .max_by_key(|p| p.dir.components().count())
+        {
+            affected.insert(pkg.name.clone());
+        }
+    }
+    // Add reverse dependencies until nothing is added
+    loop {
+        let dependents = packages
+            .iter()
+            .filter(|p| !affected.contains(&p.name))
+            .filter(|p| p.deps.iter().any(|d| affected.contains(d)))
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>();
+        if dependents.is_empty() {
+            return Ok(Some(affected.into_iter().collect()));
+        }
+        affected.extend(dependents);
+    }
+}
+
+// Output of the Git command run in the current directory
+fn git_output(args: &[&str]) -> Result<String> {
+    let out = git::output(Path::new("."), args)?;
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+// Files changed by the commit or the push, relative to the top-level directory of the repository.
+// `None` means changes are unknown for the hook.
+fn changed_files(hook: &str) -> Result<Option<Vec<String>>> {
+    let out = match hook {
+        "pre-commit" => git_output(&[
+            "-c",
+            "core.quotePath=false",
+            "diff",
+            "--cached",
+            "--name-only",
+            "-z",
+        ])?,
+        // Commits which are not pushed to any remote yet
+        "pre-push" => git_output(&[
+            "-c",
+            "core.quotePath=false",
+            "log",
+            "--format=",
+            "--name-only",
+            "-z",
+            "HEAD",
+            "--not",
+            "--remotes",
+        ])?,
+        _ => return Ok(None),
+    };
+    Ok(Some(
+        out.split(['\0', '\n'])
+            .filter(|f| !f.is_empty())
+            .map(String::from)
+            .collect(),
+    ))
+}
+
+/// Workspace members affected by changes of the hook. `None` means all members should be checked.
+pub fn affected_by_hook(hook: &str) -> Result<Option<Vec<String>>> {
+    let files = match changed_files(hook)? {
+        Some(files) => files,
+        None => return Ok(None),
+    };
+    let toplevel = git_output(&["rev-parse", "--show-toplevel"])?;
+    let toplevel = canonicalize(Path::new(toplevel.trim_end()));
+    let files = files.iter().map(|f| toplevel.join(f)).collect::<Vec<_>>();
+    let metadata = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .output()?;
+    if !metadata.status.success() {
+        return Err(Error::Metadata(
+            String::from_utf8_lossy(&metadata.stderr).trim().to_string(),
+        ));
+    }
+    affected_packages(&String::from_utf8_lossy(&metadata.stdout), &files).map_err(Error::Metadata)
+}
@@ -0,0 +1,75 @@
+use error::{Error, Result};
+use hook::{permission_bits, write_executable_file, DEFAULT_HOOK_MODE};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory where backups of replaced hook scripts are kept.
+pub fn backup_dir(hooks_dir: &Path) -> PathBuf {
+    hooks_dir.join("backup")
+}
+
+fn backup_timestamp(path: &Path, hook: &str) -> Option<u64> {
+    let name = path.file_name()?.to_str()?;
+    if !name.starts_with(hook) {
+        return None;
+    }
+    let suffix = &name[hook.len()..];
+    if !suffix.starts_with('.') {
+        return None;
+    }
+    suffix[1..].parse().ok()
+}
+
+/// Copy the hook script to `.git/hooks/backup/{hook}.{unix timestamp}` and return the path of the
+/// backup file.
+pub fn backup_hook(hook_path: &Path) -> Result<PathBuf> {
+    let hooks_dir = hook_path.parent().unwrap();
+    let hook = hook_path.file_name().unwrap().to_string_lossy();
+    let dir = backup_dir(hooks_dir);
+    fs::create_dir_all(&dir)?;
+
+    let mut secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    // Make the timestamp unique when the hook is backed up several times in a second
+    let mut backup_path = dir.join(format!("{}.{}", hook, secs));
+    while backup_path.exists() {
+        secs += 1;
+        backup_path = dir.join(format!("{}.{}", hook, secs));
+    }
+
+    fs::copy(hook_path, &backup_path)?;
+    Ok(backup_path)
+}
+
+/// List backups of the hook in `hooks_dir`. They are sorted from oldest to newest.
+pub fn backups(hooks_dir: &Path, hook: &str) -> Result<Vec<PathBuf>> {
+    let dir = backup_dir(hooks_dir);
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut found = fs::read_dir(&dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter_map(|p| backup_timestamp(&p, hook).map(|t| (t, p)))
+        .collect::<Vec<_>>();
+    found.sort();
+    Ok(found.into_iter().map(|(_, p)| p).collect())
+}
+
+/// Restore the latest backup of the hook in `hooks_dir` and return the path of the restored
+/// backup file.
+pub fn restore_hook(hooks_dir: &Path, hook: &str) -> Result<PathBuf> {
+    let hook_path = hooks_dir.join(hook);
+    let latest = match backups(hooks_dir, hook)?.pop() {
+        Some(p) => p,
+        None => return Err(Error::BackupNotFound(hook_path)),
+    };
+
+    // The backup was copied with the permissions of the hook
+    let mode = permission_bits(&latest).unwrap_or(DEFAULT_HOOK_MODE);
+    write_executable_file(&hook_path, &fs::read(&latest)?, mode)?;
+    Ok(latest)
+}
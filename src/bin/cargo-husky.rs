@@ -0,0 +1,179 @@
+extern crate cargo_husky;
+
+use cargo_husky::{find_gitdir, find_work_tree, Result, TagMismatch};
+use std::env;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+const USAGE: &str = "Usage: cargo husky <command> [args...]
+
+Commands:
+    status            Show the repository where hooks are installed
+    restore <hook>    Restore the latest backup of the hook script
+    backups <hook>    List backups of the hook script from oldest to newest
+    verify            Check installed hooks match the configuration in Cargo.toml
+    install <dir>     Install hooks generated with advise-only feature in the directory
+    run <hook>        Run commands of the hook installed with native-runner feature
+    run secrets       Scan staged changes for credentials
+    run lockfile      Check Cargo.lock is staged with changes of dependencies
+    run denylist <pattern>...
+                      Check no staged file matches the glob patterns
+    run version-tag <manifest> [<changelog>]
+                      Check versions of release tags pushed from stdin
+    help              Show this help
+    --version         Show version";
+
+fn hooks_dir() -> Result<PathBuf> {
+    let cwd = env::current_dir()?;
+    Ok(cargo_husky::hooks_dir(&find_gitdir(&cwd)?))
+}
+
+fn run(args: &[String]) -> Result<i32> {
+    match (args.first().map(String::as_str), args.get(1)) {
+        (Some("status"), _) => {
+            let cwd = env::current_dir()?;
+            let gitdir = find_gitdir(&cwd)?;
+            println!("Git directory: {}", gitdir.display());
+            println!("Working tree: {}", find_work_tree(&cwd)?.display());
+            println!(
+                "Hooks directory: {}",
+                cargo_husky::hooks_dir(&gitdir).display()
+            );
+        }
+        (Some("restore"), Some(hook)) => {
+            let backup = cargo_husky::restore_hook(&hooks_dir()?, hook)?;
+            println!("Restored {} from {}", hook, backup.display());
+        }
+        (Some("backups"), Some(hook)) => {
+            for backup in cargo_husky::backups(&hooks_dir()?, hook)? {
+                println!("{}", backup.display());
+            }
+        }
+        (Some("install"), Some(dir)) => {
+            for hook in cargo_husky::install_advised_hooks(Path::new(dir), &hooks_dir()?)? {
+                println!("Installed {}", hook);
+            }
+        }
+        (Some("verify"), _) => {
+            let drifts = cargo_husky::verify_hooks(&hooks_dir()?)?;
+            if drifts.is_empty() {
+                return Ok(0);
+            }
+            for d in &drifts {
+                eprintln!("{}", d);
+            }
+            eprintln!("cargo-husky: Installed hooks don't match the configuration. Run 'cargo test' to install them again");
+            return Ok(1);
+        }
+        (Some("run"), Some(check)) if check == "secrets" => {
+            let findings = cargo_husky::scan_staged(&env::current_dir()?)?;
+            if findings.is_empty() {
+                return Ok(0);
+            }
+            for f in &findings {
+                eprintln!("{}:{}: Found {}", f.file, f.line, f.kind);
+            }
+            eprintln!(
+                "cargo-husky: Credentials were found in staged changes. Add '{}' comment to the line if it is not a credential",
+                cargo_husky::SECRETS_ALLOW_COMMENT
+            );
+            return Ok(1);
+        }
+        (Some("run"), Some(check)) if check == "lockfile" => {
+            let stale = cargo_husky::check_staged_lockfiles(&env::current_dir()?)?;
+            if stale.is_empty() {
+                return Ok(0);
+            }
+            for s in &stale {
+                eprintln!(
+                    "{}: Dependencies were changed but {} is not staged",
+                    s.manifest, s.lockfile
+                );
+            }
+            eprintln!("cargo-husky: Update Cargo.lock by building the crate (e.g. 'cargo check') and stage it");
+            return Ok(1);
+        }
+        (Some("run"), Some(check)) if check == "denylist" => {
+            let denied = cargo_husky::check_staged_denylist(&env::current_dir()?, &args[2..])?;
+            if denied.is_empty() {
+                return Ok(0);
+            }
+            for d in &denied {
+                eprintln!("{}: Matched denied pattern '{}'", d.file, d.pattern);
+            }
+            eprintln!(
+                "cargo-husky: Denied files were staged. Unstage them by 'git rm --cached <file>'"
+            );
+            return Ok(1);
+        }
+        (Some("run"), Some(check)) if check == "version-tag" => {
+            let manifest = match args.get(2) {
+                Some(manifest) => manifest,
+                None => {
+                    eprintln!("{}", USAGE);
+                    return Ok(1);
+                }
+            };
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            let mismatches = cargo_husky::check_pushed_tags(
+                &env::current_dir()?,
+                &input,
+                manifest,
+                args.get(3).map(String::as_str),
+            )?;
+            if mismatches.is_empty() {
+                return Ok(0);
+            }
+            for m in &mismatches {
+                match m {
+                    TagMismatch::Version {
+                        tag,
+                        manifest,
+                        version: Some(version),
+                    } => eprintln!(
+                        "{}: Version in {} is {} but the tag is not for it",
+                        tag, manifest, version
+                    ),
+                    TagMismatch::Version { tag, manifest, .. } => {
+                        eprintln!("{}: No version is found in {}", tag, manifest)
+                    }
+                    TagMismatch::Changelog { tag, changelog } => {
+                        eprintln!(
+                            "{}: No entry for the version is found in {}",
+                            tag, changelog
+                        )
+                    }
+                }
+            }
+            eprintln!("cargo-husky: Release tags don't match the tagged commits. Fix them and recreate the tags");
+            return Ok(1);
+        }
+        (Some("run"), Some(hook)) => return cargo_husky::run_hook(&hooks_dir()?, hook),
+        (Some("--version"), _) | (Some("-V"), _) => {
+            println!("cargo-husky {}", env!("CARGO_PKG_VERSION"))
+        }
+        (Some("help"), _) | (Some("--help"), _) | (Some("-h"), _) => println!("{}", USAGE),
+        _ => {
+            eprintln!("{}", USAGE);
+            return Ok(1);
+        }
+    }
+    Ok(0)
+}
+
+fn main() {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    // `cargo husky ...` runs `cargo-husky husky ...`
+    if args.first().map(String::as_str) == Some("husky") {
+        args.remove(0);
+    }
+    match run(&args) {
+        Ok(code) => exit(code),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            exit(1);
+        }
+    }
+}
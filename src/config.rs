@@ -0,0 +1,208 @@
+use error::{Error, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{self, Table, Value};
+
+/// Configuration in `[package.metadata.husky]` (or `[workspace.metadata.husky]`) section of
+/// Cargo.toml of the project.
+#[derive(Default)]
+pub struct Config {
+    table: Table,
+    edition: Option<String>,
+    rust_version: Option<String>,
+    package_name: Option<String>,
+    manifest_dir: Option<PathBuf>,
+    features: Vec<String>,
+}
+
+/// Find Cargo.toml of the project from `start` or its parent directories until `root` directory.
+/// Build script passes `$OUT_DIR` as `start`, which is put in the target directory of the project.
+pub fn find_manifest(start: &Path, root: &Path) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        let manifest = dir.join("Cargo.toml");
+        if manifest.is_file() {
+            return Some(manifest);
+        }
+        if dir == root {
+            break;
+        }
+    }
+    // When the target directory is outside the repository
+    let manifest = root.join("Cargo.toml");
+    if manifest.is_file() {
+        Some(manifest)
+    } else {
+        None
+    }
+}
+
+/// Hash of the bytes as a hex string. It is FNV-1a. Unlike `DefaultHasher`, the result is stable
+/// across Rust versions and machines.
+pub fn stable_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ *b as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+impl Config {
+    pub fn load(manifest: &Path) -> Result<Config> {
+        let src = fs::read_to_string(manifest)?;
+        let root = toml::parse(&src).map_err(|msg| Error::Manifest(manifest.to_owned(), msg))?;
+        let table = [
+            &["package", "metadata", "husky"],
+            &["workspace", "metadata", "husky"],
+        ]
+        .iter()
+        .filter_map(|path| match toml::lookup(&root, &path[..]) {
+            Some(Value::Table(t)) => Some(t.clone()),
+            _ => None,
+        })
+        .next()
+        .unwrap_or_default();
+        let package_str = |key| {
+            [&["package", key][..], &["workspace", "package", key][..]]
+                .iter()
+                .filter_map(|path| match toml::lookup(&root, path) {
+                    Some(Value::String(s)) => Some(s.clone()),
+                    _ => None,
+                })
+                .next()
+        };
+        let edition = package_str("edition");
+        let rust_version = package_str("rust-version");
+        let package_name = match toml::lookup(&root, &["package", "name"]) {
+            Some(Value::String(s)) => Some(s.clone()),
+            _ => None,
+        };
+        Ok(Config {
+            table,
+            edition,
+            rust_version,
+            package_name,
+            manifest_dir: manifest.parent().map(Path::to_path_buf),
+            features: vec![],
+        })
+    }
+
+    /// Set features of cargo-husky enabled by the project. They are a part of the configuration
+    /// identified by `hash`.
+    pub fn with_features(mut self, features: Vec<String>) -> Config {
+        self.features = features;
+        self
+    }
+
+    /// Hash of the effective configuration: enabled features, the configuration in package metadata
+    /// and the package information used by hooks. Generated hooks record it in their headers.
+    pub fn hash(&self) -> String {
+        let src = format!(
+            "{:?}\n{:?}\n{:?}\n{:?}",
+            self.features, self.table, self.edition, self.rust_version
+        );
+        stable_hash(src.as_bytes())
+    }
+
+    /// Features of cargo-husky set by `with_features`.
+    pub fn features(&self) -> &[String] {
+        &self.features
+    }
+
+    /// Rust edition of the package. It is necessary to run rustfmt directly.
+    pub fn edition(&self) -> Option<&str> {
+        self.edition.as_deref()
+    }
+
+    /// Minimum supported Rust version declared in `rust-version`.
+    pub fn rust_version(&self) -> Option<&str> {
+        self.rust_version.as_deref()
+    }
+
+    /// Name of the package. It is `None` for virtual workspace.
+    pub fn package_name(&self) -> Option<&str> {
+        self.package_name.as_deref()
+    }
+
+    /// Directory which contains Cargo.toml of the project.
+    pub fn manifest_dir(&self) -> Option<&Path> {
+        self.manifest_dir.as_deref()
+    }
+
+    fn value(&self, key: &str) -> Option<&Value> {
+        let path = key.split('.').collect::<Vec<_>>();
+        toml::lookup(&self.table, &path)
+    }
+
+    fn invalid(key: &str, expected: &'static str, actual: &Value) -> Error {
+        Error::InvalidConfig(key.to_string(), expected, actual.type_name())
+    }
+
+    /// Get a string value by dotted key such as `semver-checks.baseline-rev`.
+    pub fn get_str(&self, key: &str) -> Result<Option<&str>> {
+        match self.value(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s)),
+            Some(v) => Err(Config::invalid(key, "string", v)),
+        }
+    }
+
+    /// Get a boolean value by dotted key.
+    pub fn get_bool(&self, key: &str) -> Result<Option<bool>> {
+        match self.value(key) {
+            None => Ok(None),
+            Some(Value::Boolean(b)) => Ok(Some(*b)),
+            Some(v) => Err(Config::invalid(key, "boolean", v)),
+        }
+    }
+
+    /// Get an integer value by dotted key.
+    pub fn get_int(&self, key: &str) -> Result<Option<i64>> {
+        match self.value(key) {
+            None => Ok(None),
+            Some(Value::Integer(i)) => Ok(Some(*i)),
+            Some(v) => Err(Config::invalid(key, "integer", v)),
+        }
+    }
+
+    /// Get an array of strings by dotted key.
+    pub fn get_str_array(&self, key: &str) -> Result<Option<Vec<&str>>> {
+        let elems = match self.value(key) {
+            None => return Ok(None),
+            Some(Value::Array(elems)) => elems,
+            Some(v) => return Err(Config::invalid(key, "array of strings", v)),
+        };
+        let mut strs = Vec::with_capacity(elems.len());
+        for elem in elems {
+            match elem {
+                Value::String(s) => strs.push(s.as_str()),
+                v => return Err(Config::invalid(key, "array of strings", v)),
+            }
+        }
+        Ok(Some(strs))
+    }
+
+    /// Get keys of a table by dotted key.
+    pub fn get_table_keys(&self, key: &str) -> Result<Option<Vec<&str>>> {
+        match self.value(key) {
+            None => Ok(None),
+            Some(Value::Table(t)) => Ok(Some(t.keys().map(String::as_str).collect())),
+            Some(v) => Err(Config::invalid(key, "table", v)),
+        }
+    }
+
+    /// Get a table whose values are strings by dotted key.
+    pub fn get_str_table(&self, key: &str) -> Result<Option<Vec<(&str, &str)>>> {
+        let table = match self.value(key) {
+            None => return Ok(None),
+            Some(Value::Table(t)) => t,
+            Some(v) => return Err(Config::invalid(key, "table of strings", v)),
+        };
+        let mut pairs = Vec::with_capacity(table.len());
+        for (k, v) in table {
+            match v {
+                Value::String(s) => pairs.push((k.as_str(), s.as_str())),
+                v => return Err(Config::invalid(key, "table of strings", v)),
+            }
+        }
+        Ok(Some(pairs))
+    }
+}
@@ -0,0 +1,97 @@
+use error::Result;
+use git;
+use std::path::Path;
+
+/// Staged file matched by a pattern of the denylist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeniedFile {
+    pub file: String,
+    pub pattern: String,
+}
+
+// Match one path component with `*` and `?` wildcards
+fn match_component(pat: &[char], name: &[char]) -> bool {
+    match pat.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|i| match_component(rest, &name[i..])),
+        Some(('?', rest)) => !name.is_empty() && match_component(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && match_component(rest, &name[1..]),
+    }
+}
+
+// Match path components. `**` matches zero or more components.
+fn match_components(pats: &[&str], names: &[&str]) -> bool {
+    match pats.split_first() {
+        None => names.is_empty(),
+        Some((&"**", rest)) => (0..=names.len()).any(|i| match_components(rest, &names[i..])),
+        Some((pat, rest)) => match names.split_first() {
+            Some((name, names)) => {
+                let pat = pat.chars().collect::<Vec<_>>();
+                let name = name.chars().collect::<Vec<_>>();
+                match_component(&pat, &name) && match_components(rest, names)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Whether the path relative to the repository root matches the glob pattern. Like `.gitignore`, a
+/// pattern without `/` such as `*.pem` matches the file name in any directory, and a pattern ending
+/// with `/` matches all files in the directory.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let mut pattern = pattern.trim_start_matches('/').to_string();
+    if pattern.ends_with('/') {
+        pattern.push_str("**");
+    }
+    let names = path.split('/').collect::<Vec<_>>();
+    if !pattern.contains('/') {
+        return names
+            .iter()
+            .any(|name| match_components(&[pattern.as_str()], &[name]));
+    }
+    let pats = pattern.split('/').collect::<Vec<_>>();
+    match_components(&pats, &names)
+}
+
+/// Files which match any of the patterns, with the first matched pattern.
+pub fn denied_files<S: AsRef<str>>(files: &[S], patterns: &[S]) -> Vec<DeniedFile> {
+    files
+        .iter()
+        .filter_map(|file| {
+            let file = file.as_ref();
+            patterns
+                .iter()
+                .map(AsRef::as_ref)
+                .find(|pattern| glob_match(pattern, file))
+                .map(|pattern| DeniedFile {
+                    file: file.to_string(),
+                    pattern: pattern.to_string(),
+                })
+        })
+        .collect()
+}
+
+/// Check files staged in the repository at `dir` against the denylist. Deleted files are not
+/// checked.
+pub fn check_staged<S: AsRef<str>>(dir: &Path, patterns: &[S]) -> Result<Vec<DeniedFile>> {
+    let out = git::output(
+        dir,
+        &[
+            "-c",
+            "core.quotePath=false",
+            "diff",
+            "--cached",
+            "--name-only",
+            "--no-relative",
+            "--diff-filter=ACMR",
+            "-z",
+        ],
+    )?;
+    let stdout = String::from_utf8_lossy(&out);
+    let files = stdout
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .collect::<Vec<_>>();
+    let patterns = patterns.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+    Ok(denied_files(&files, &patterns))
+}
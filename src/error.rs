@@ -0,0 +1,196 @@
+use script::COMMANDS;
+use std::path::PathBuf;
+use std::{env, error, fmt, io};
+
+pub enum Error {
+    GitDirNotFound(PathBuf),
+    InvalidGitFile(PathBuf),
+    BareRepository(PathBuf),
+    #[allow(dead_code)] // Not constructed in library
+    NonUtf8Path(PathBuf),
+    Io(io::Error),
+    OutDir(env::VarError),
+    InvalidUserHooksDir(PathBuf),
+    EmptyUserHook(PathBuf),
+    InvalidUserHook(PathBuf, String),
+    ConflictingUserHook(PathBuf),
+    ForeignHook(PathBuf),
+    ChainConflict(PathBuf),
+    NotOwner(PathBuf),
+    #[allow(dead_code)] // Not constructed in library
+    HooksDirNotWritable(PathBuf, io::Error),
+    NewerHook(PathBuf),
+    IncludeNotFound(PathBuf, String),
+    RecursiveInclude(PathBuf),
+    BackupNotFound(PathBuf),
+    Manifest(PathBuf, String),
+    InvalidConfig(String, &'static str, &'static str),
+    UnknownCommand(String, String),
+    UnknownExportFormat(String),
+    RustVersionNotFound,
+    #[allow(dead_code)] // Not constructed in library
+    InvalidFeatures(Vec<String>),
+    Git(String),
+    InvalidPlan(PathBuf, String),
+    HooksNotInstalled(PathBuf),
+    InvalidInstallRecord(PathBuf, String),
+    #[allow(dead_code)] // Not constructed in library
+    UserConfig(PathBuf, String),
+    #[allow(dead_code)] // Not constructed in build script
+    Metadata(String),
+}
+
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+impl From<env::VarError> for Error {
+    fn from(error: env::VarError) -> Error {
+        Error::OutDir(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::GitDirNotFound(dir) => write!(
+                f,
+                ".git directory was not found in '{}' or its parent directories and $GIT_DIR is not set",
+                dir.display(),
+            ),
+            Error::InvalidGitFile(path) => write!(
+                f,
+                "{:?} does not point to a Git directory with 'gitdir: <path>' line",
+                path
+            ),
+            Error::BareRepository(path) => write!(
+                f,
+                "{:?} is a bare repository. Hooks are not installed in bare repositories since they have no working tree",
+                path
+            ),
+            Error::NonUtf8Path(path) => write!(
+                f,
+                "Path {:?} cannot be written to hook scripts or config files since it is not valid UTF-8",
+                path
+            ),
+            Error::Io(inner) => write!(f, "IO error: {}", inner),
+            Error::OutDir(env::VarError::NotPresent) => unreachable!(),
+            Error::OutDir(env::VarError::NotUnicode(msg)) => {
+                write!(f, "{}", msg.to_string_lossy())
+            }
+            Error::InvalidUserHooksDir(path) => write!(
+                f,
+                "User hooks directory is not found or no executable file is found in '{:?}'. Did you forget to make a hook script executable?",
+                path
+            ),
+            Error::EmptyUserHook(path) => write!(f, "User hook script is empty: {:?}", path),
+            Error::InvalidUserHook(path, msg) => {
+                write!(f, "User hook script {:?} is invalid: {}", path, msg)
+            }
+            Error::ConflictingUserHook(path) => write!(
+                f,
+                "User hook script {:?} conflicts with '.d' directory of the same hook. Please move the script into the directory",
+                path
+            ),
+            Error::ForeignHook(path) => write!(
+                f,
+                "Hook {:?} was not installed since another hook script is already there",
+                path
+            ),
+            Error::ChainConflict(path) => write!(
+                f,
+                "Cannot chain the existing hook since {:?} already exists. Please merge the hooks manually",
+                path
+            ),
+            Error::NotOwner(path) => write!(
+                f,
+                "Hooks were not installed since {:?} is owned by another user. Add the repository to 'safe.directory' of Git config to install them",
+                path
+            ),
+            Error::HooksDirNotWritable(path, err) => write!(
+                f,
+                "Hooks were not installed since hooks directory {:?} is not writable: {}",
+                path, err
+            ),
+            Error::NewerHook(path) => write!(
+                f,
+                "Hook {:?} was generated by a newer version of cargo-husky. It is not replaced by v{}",
+                path,
+                env!("CARGO_PKG_VERSION")
+            ),
+            Error::IncludeNotFound(path, include) => write!(
+                f,
+                "File '{}' included by user hook script {:?} is not found in .cargo-husky/lib directory",
+                include, path
+            ),
+            Error::RecursiveInclude(path) => {
+                write!(f, "File {:?} is recursively included by user hook script", path)
+            }
+            Error::BackupNotFound(path) => write!(f, "No backup of hook {:?} was found", path),
+            Error::Manifest(path, msg) => write!(f, "Could not parse {:?}: {}", path, msg),
+            Error::InvalidConfig(key, expected, actual) => write!(
+                f,
+                "Invalid value for '{}' in [package.metadata.husky] section of Cargo.toml: expected {} but got {}",
+                key, expected, actual
+            ),
+            Error::UnknownExportFormat(format) => write!(
+                f,
+                "Unknown format '{}' in $CARGO_HUSKY_EXPORT. Available format is 'pre-commit'",
+                format
+            ),
+            Error::RustVersionNotFound => write!(
+                f,
+                "'rust-version' is not declared in Cargo.toml. It is necessary to check the crate with its MSRV. Declare it or set 'msrv.toolchain' in [package.metadata.husky] section",
+            ),
+            Error::InvalidFeatures(problems) => {
+                write!(f, "Invalid combination of cargo-husky features:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+            Error::UnknownCommand(key, name) => write!(
+                f,
+                "Unknown command '{}' for '{}' in [package.metadata.husky] section of Cargo.toml. Available commands are '{}' and custom commands defined by 'commands.<name>.run'",
+                name,
+                key,
+                COMMANDS.join("', '")
+            ),
+            Error::Git(stderr) => write!(f, "git command failed: {}", stderr),
+            Error::InvalidPlan(path, msg) => write!(
+                f,
+                "Could not load commands of hook from {:?}: {}. Please run 'cargo test' to install hooks again",
+                path, msg
+            ),
+            Error::HooksNotInstalled(path) => write!(
+                f,
+                "No hook installed by cargo-husky was found in {:?}. Please run 'cargo test' to install hooks",
+                path
+            ),
+            Error::InvalidInstallRecord(path, msg) => write!(
+                f,
+                "Could not load installed hooks from {:?}: {}. Please run 'cargo test' to install hooks again",
+                path, msg
+            ),
+            Error::UserConfig(path, msg) => write!(f, "Invalid user config {:?}: {}", path, msg),
+            Error::Metadata(msg) => write!(
+                f,
+                "Could not read workspace members with 'cargo metadata': {}",
+                msg
+            ),
+        }
+    }
+}
+
+// Build script returns this error from main(). Debug output is shown to users as-is.
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl error::Error for Error {}
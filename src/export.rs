@@ -0,0 +1,83 @@
+use plan::Step;
+use toml::string_literal;
+
+// ID of the hook in the configuration. Steps of the same command are distinguished by the hook and
+// the position since a command can be run for each feature set.
+fn hook_id(hook: &str, step: &Step, index: usize) -> String {
+    let name = step
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>();
+    if index == 0 {
+        format!("cargo-husky-{}-{}", hook, name)
+    } else {
+        format!("cargo-husky-{}-{}-{}", hook, name, index + 1)
+    }
+}
+
+// Regular expression of `files` matching the pathspec such as `*/Cargo.toml`
+fn files_regex(pathspec: &[String]) -> String {
+    let alts = pathspec
+        .iter()
+        .map(|p| {
+            p.chars()
+                .map(|c| match c {
+                    '*' => ".*".to_string(),
+                    '?' => ".".to_string(),
+                    c if "\\.+()[]{}^$|".contains(c) => format!("\\{}", c),
+                    c => c.to_string(),
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>();
+    format!("^({})$", alts.join("|"))
+}
+
+// The entry is split into words by the framework like shell. Environment variables are set by `env`.
+fn entry(step: &Step) -> String {
+    if step.env.is_empty() {
+        step.display()
+    } else {
+        format!("env {}", step.display())
+    }
+}
+
+/// Render `.pre-commit-config.yaml` of the pre-commit framework (https://pre-commit.com). `hooks` is
+/// a list of Git hooks and their commands. Each command is rendered as a local hook run at the
+/// stage of the Git hook. Staged Rust files are passed to the command which accepts them.
+pub fn render_pre_commit_config(hooks: &[(&str, Vec<Step>)]) -> String {
+    let mut s = format!(
+        "# Generated by cargo-husky v{}: {}\ndefault_install_hook_types: [{}]\nrepos:\n  - repo: local\n    hooks:\n",
+        env!("CARGO_PKG_VERSION"),
+        env!("CARGO_PKG_HOMEPAGE"),
+        hooks
+            .iter()
+            .map(|(hook, _)| *hook)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    for (hook, steps) in hooks {
+        for (i, step) in steps.iter().enumerate() {
+            let index = steps[..i].iter().filter(|s| s.name == step.name).count();
+            s += &format!(
+                "      - id: {}\n        name: {}\n        entry: {}\n        language: system\n",
+                hook_id(hook, step, index),
+                string_literal(&step.display()),
+                string_literal(&entry(step))
+            );
+            if step.staged && step.pathspec.is_empty() {
+                s += "        types: [rust]\n";
+            } else if step.staged {
+                s += &format!(
+                    "        files: {}\n",
+                    string_literal(&files_regex(&step.pathspec))
+                );
+            } else {
+                s += "        pass_filenames: false\n        always_run: true\n";
+            }
+            s += &format!("        stages: [{}]\n", hook);
+        }
+    }
+    s
+}
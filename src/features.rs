@@ -0,0 +1,157 @@
+// Validation of combinations of cargo-husky features. Features enabled by default are not reported
+// since users cannot disable them without `default-features = false`.
+
+const DEFAULT_FEATURES: &[&str] = &["prepush-hook", "run-cargo-test", "run-for-all"];
+
+// Features only for pre-commit hook
+const PRE_COMMIT_FEATURES: &[&str] = &[
+    "run-cargo-doctest",
+    "staged-only",
+    "stash-unstaged",
+    "autofix",
+    "skip-in-progress",
+    "protect-branches",
+    "check-markers",
+    "check-secrets",
+    "check-lockfile",
+    "check-denylist",
+    "check-large-files",
+];
+
+// Features only for pre-push hook
+const PRE_PUSH_FEATURES: &[&str] = &[
+    "skip-unchanged",
+    "run-cargo-miri",
+    "run-cargo-hack",
+    "run-cargo-semver-checks",
+    "run-cargo-publish",
+    "check-version-tag",
+];
+
+// Features which change generated hooks other than the above
+const GENERATOR_FEATURES: &[&str] = &[
+    "native-runner",
+    "chain-hooks",
+    "ask-consent",
+    "locked",
+    "offline",
+    "frozen",
+    "all-targets",
+];
+
+fn is_hook(feature: &str) -> bool {
+    feature.ends_with("-hook")
+}
+
+fn is_command(feature: &str) -> bool {
+    feature.starts_with("run-") && feature != "run-for-all"
+}
+
+fn affects_generated_hooks(feature: &str) -> bool {
+    is_hook(feature)
+        || is_command(feature)
+        || PRE_COMMIT_FEATURES.contains(&feature)
+        || PRE_PUSH_FEATURES.contains(&feature)
+        || GENERATOR_FEATURES.contains(&feature)
+}
+
+// 'a', 'b' and 'c'
+fn list(features: &[&str]) -> String {
+    let quoted = features
+        .iter()
+        .map(|f| format!("'{}'", f))
+        .collect::<Vec<_>>();
+    match quoted.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        _ => quoted.join(""),
+    }
+}
+
+fn verb(features: &[&str]) -> &'static str {
+    if features.len() == 1 {
+        "has"
+    } else {
+        "have"
+    }
+}
+
+/// Problems of the enabled features with suggested fixes. Empty means the combination is valid.
+pub fn validate<S: AsRef<str>>(enabled: &[S]) -> Vec<String> {
+    let enabled = enabled.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+    let has = |feature: &str| enabled.contains(&feature);
+    let mut problems = vec![];
+
+    for feature in &["combine-hooks", "symlink-user-hooks"] {
+        if has(feature) && !has("user-hooks") {
+            problems.push(format!(
+                "'{0}' has no effect without 'user-hooks'. Enable 'user-hooks' or remove '{0}'",
+                feature
+            ));
+        }
+    }
+
+    if has("advise-only") && has("user-hooks") {
+        problems.push(
+            "'advise-only' does not generate user hooks. Remove 'user-hooks' or install user hooks by hand"
+                .to_string(),
+        );
+    }
+
+    if has("user-hooks") && !has("combine-hooks") {
+        let ignored = enabled
+            .iter()
+            .copied()
+            .filter(|f| affects_generated_hooks(f) && !DEFAULT_FEATURES.contains(f))
+            .collect::<Vec<_>>();
+        if !ignored.is_empty() {
+            problems.push(format!(
+                "{} {} no effect with 'user-hooks' since only user hooks are installed. Enable 'combine-hooks' to install generated hooks with user hooks, or remove {}",
+                list(&ignored),
+                verb(&ignored),
+                if ignored.len() == 1 { "it" } else { "them" },
+            ));
+        }
+        return problems;
+    }
+
+    if !enabled.iter().any(|f| is_hook(f)) {
+        let ignored = enabled
+            .iter()
+            .copied()
+            .filter(|f| affects_generated_hooks(f))
+            .collect::<Vec<_>>();
+        if !ignored.is_empty() {
+            problems.push(format!(
+                "{} {} no effect since no hook is generated. Enable a hook feature such as 'precommit-hook' or 'prepush-hook'",
+                list(&ignored),
+                verb(&ignored),
+            ));
+        }
+        return problems;
+    }
+
+    for (hook, features) in &[
+        ("precommit-hook", PRE_COMMIT_FEATURES),
+        ("prepush-hook", PRE_PUSH_FEATURES),
+    ] {
+        if has(hook) {
+            continue;
+        }
+        let ignored = enabled
+            .iter()
+            .copied()
+            .filter(|f| features.contains(f))
+            .collect::<Vec<_>>();
+        if !ignored.is_empty() {
+            problems.push(format!(
+                "{} {} no effect without '{}'. Enable '{}' or remove {}",
+                list(&ignored),
+                verb(&ignored),
+                hook,
+                hook,
+                if ignored.len() == 1 { "it" } else { "them" },
+            ));
+        }
+    }
+    problems
+}
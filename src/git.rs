@@ -0,0 +1,284 @@
+use error::{Error, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Path written by Git as bytes such as content of `.git` file or output of `git config`. Paths are
+/// not always valid UTF-8 on Unix.
+#[cfg(unix)]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+/// Path written by Git as bytes such as content of `.git` file or output of `git config`. Git for
+/// Windows writes paths in UTF-8.
+#[cfg(not(unix))]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+// Read a path written in the file. A relative path is relative to `base`.
+fn read_path(file: &Path, base: &Path) -> Option<PathBuf> {
+    let buf = fs::read(file).ok()?;
+    let mut path = &buf[..];
+    while let [rest @ .., b'\n' | b'\r'] = path {
+        path = rest;
+    }
+    if path.is_empty() {
+        return None;
+    }
+    Some(base.join(path_from_bytes(path)))
+}
+
+// Git repository of the Jujutsu repository in `jj_dir` (`.jj` directory). `.jj/repo` is a file
+// pointing to the repository in a secondary workspace. The Git repository is in `store/git` unless
+// `store/git_target` points to another one.
+fn jj_gitdir(jj_dir: &Path) -> Option<PathBuf> {
+    let mut repo = jj_dir.join("repo");
+    if repo.is_file() {
+        repo = read_path(&repo, jj_dir)?;
+    }
+    let store = repo.join("store");
+    let gitdir = read_path(&store.join("git_target"), &store).unwrap_or_else(|| store.join("git"));
+    if gitdir.is_dir() {
+        Some(fs::canonicalize(&gitdir).unwrap_or(gitdir))
+    } else {
+        None
+    }
+}
+
+// Path in `$GIT_DIR` or `$GIT_WORK_TREE`. A relative path is relative to the current directory as Git
+// does.
+fn env_path(name: &str) -> Result<Option<PathBuf>> {
+    match env::var_os(name) {
+        Some(path) if !path.is_empty() => Ok(Some(env::current_dir()?.join(path))),
+        _ => Ok(None),
+    }
+}
+
+// Git directory pointed by `.git` file of a worktree, a submodule or a repository created with
+// `git init --separate-git-dir`. The file contains `gitdir: <path>`.
+fn read_gitdir_file(file: &Path) -> Result<PathBuf> {
+    let buf = fs::read(file)?;
+    let path = match buf.trim_ascii_end().strip_prefix(b"gitdir:") {
+        Some(path) => path.trim_ascii(),
+        None => return Err(Error::InvalidGitFile(file.to_owned())),
+    };
+    let gitdir = file.parent().unwrap_or(file).join(path_from_bytes(path));
+    if !gitdir.is_dir() {
+        return Err(Error::InvalidGitFile(file.to_owned()));
+    }
+    Ok(gitdir)
+}
+
+// Value of the key in the section of a Git config file. Subsections and includes are not supported.
+// The value is bytes since it may be a path.
+fn config_value(file: &Path, section: &str, key: &str) -> Option<Vec<u8>> {
+    let config = fs::read(file).ok()?;
+    let mut in_section = false;
+    for line in config.split(|b| *b == b'\n').map(<[u8]>::trim_ascii) {
+        if let Some(name) = line.strip_prefix(b"[") {
+            let name = name.strip_suffix(b"]").unwrap_or(name);
+            in_section = name.trim_ascii().eq_ignore_ascii_case(section.as_bytes());
+        } else if let Some(eq) = line.iter().position(|b| *b == b'=').filter(|_| in_section) {
+            if line[..eq].trim_ascii().eq_ignore_ascii_case(key.as_bytes()) {
+                let value = line[eq + 1..].trim_ascii();
+                let unquoted = value
+                    .strip_prefix(b"\"")
+                    .and_then(|v| v.strip_suffix(b"\""));
+                return Some(unquoted.unwrap_or(value).to_vec());
+            }
+        }
+    }
+    None
+}
+
+fn is_true(value: &[u8]) -> bool {
+    ["true", "yes", "on", "1"]
+        .iter()
+        .any(|t| value.eq_ignore_ascii_case(t.as_bytes()))
+}
+
+// `core.bare` in the config of the Git directory. Bare repositories such as push targets on servers
+// have no working tree where hooks are useful.
+fn is_bare(gitdir: &Path) -> bool {
+    gitdir.join("HEAD").is_file()
+        && config_value(&gitdir.join("config"), "core", "bare").is_some_and(|v| is_true(&v))
+}
+
+fn not_bare(gitdir: PathBuf) -> Result<PathBuf> {
+    if is_bare(&gitdir) {
+        Err(Error::BareRepository(gitdir))
+    } else {
+        Ok(gitdir)
+    }
+}
+
+// Git directory and the root of its working tree. `$GIT_DIR` and `$GIT_WORK_TREE` take precedence
+// over discovery as Git does. `$GIT_DIR` which is not a directory is ignored since Git sets a relative
+// path (e.g. `.git`) while running hooks, and the current directory may be a workspace member.
+fn discover(start: &Path) -> Result<(PathBuf, PathBuf)> {
+    if let Some(gitdir) = env_path("GIT_DIR")?.filter(|d| d.is_dir()) {
+        // `$GIT_WORK_TREE` gives a working tree even to a bare repository
+        return match env_path("GIT_WORK_TREE")? {
+            Some(root) => Ok((gitdir, root)),
+            None => {
+                let gitdir = not_bare(gitdir)?;
+                let root = work_tree(&gitdir).to_owned();
+                Ok((gitdir, root))
+            }
+        };
+    }
+    discover_from(start)
+}
+
+// Git directory and the root of its working tree found from `start` or its parent directories
+// ignoring `$GIT_DIR` and `$GIT_WORK_TREE`.
+fn discover_from(start: &Path) -> Result<(PathBuf, PathBuf)> {
+    let mut dir = start.to_owned();
+    if !dir.has_root() {
+        dir = fs::canonicalize(dir)?;
+    }
+    loop {
+        let gitdir = dir.join(".git");
+        if gitdir.is_dir() {
+            return Ok((not_bare(gitdir)?, dir));
+        }
+        if gitdir.is_file() {
+            return Ok((not_bare(read_gitdir_file(&gitdir)?)?, dir));
+        }
+        // Jujutsu repository not colocated with Git. A colocated one is found by `.git` above
+        if let Some(gitdir) = jj_gitdir(&dir.join(".jj")) {
+            return Ok((gitdir, dir));
+        }
+        // The crate is in a bare repository itself
+        if is_bare(&dir) {
+            return Err(Error::BareRepository(dir));
+        }
+        if !dir.pop() {
+            return Err(Error::GitDirNotFound(start.to_owned()));
+        }
+    }
+}
+
+/// Whether `$GIT_DIR` or `$GIT_WORK_TREE` points to a repository other than the one found from
+/// `start`. Git exports `$GIT_DIR` to hooks in some cases (e.g. in a linked worktree or with
+/// `git --git-dir`) even though it is the repository found by discovery.
+#[allow(dead_code)] // Not used in library
+pub fn env_overrides_discovery(start: &Path) -> bool {
+    if env::var_os("GIT_DIR").is_none() && env::var_os("GIT_WORK_TREE").is_none() {
+        return false;
+    }
+    // `$GIT_DIR` may be a relative path such as `.git`
+    let canonical = |p: &Path| fs::canonicalize(p).unwrap_or_else(|_| p.to_owned());
+    let same_path = |a: &Path, b: &Path| canonical(a) == canonical(b);
+    match (discover(start), discover_from(start)) {
+        (Ok((dir1, root1)), Ok((dir2, root2))) => {
+            !same_path(&dir1, &dir2) || !same_path(&root1, &root2)
+        }
+        (Err(_), Err(_)) => false,
+        _ => true,
+    }
+}
+
+/// Find `.git` directory from `start` or its parent directories. When `.git` is a file (e.g. a
+/// worktree or a submodule), the Git directory pointed by it is returned. In a Jujutsu repository
+/// which is not colocated with Git, the Git repository in `.jj/repo/store/git` is returned. `$GIT_DIR`
+/// is returned when it is set.
+pub fn find_gitdir(start: &Path) -> Result<PathBuf> {
+    discover(start).map(|(gitdir, _)| gitdir)
+}
+
+/// Find the root of the working tree from `start` or its parent directories. Unlike `work_tree`, it
+/// also works for a Git directory separated from its working tree. `$GIT_WORK_TREE` is returned
+/// when it is set with `$GIT_DIR`.
+pub fn find_work_tree(start: &Path) -> Result<PathBuf> {
+    discover(start).map(|(_, root)| root)
+}
+
+/// Git directory shared by all worktrees of the repository. A linked worktree has its own Git
+/// directory in `.git/worktrees/<name>` with `commondir` file pointing to the shared one.
+pub fn common_dir(gitdir: &Path) -> PathBuf {
+    read_path(&gitdir.join("commondir"), gitdir).unwrap_or_else(|| gitdir.to_owned())
+}
+
+/// Whether Git tracks executable bit of files (`core.fileMode`). It is disabled on Windows and on file
+/// systems which do not support executable bit.
+pub fn file_mode_enabled(gitdir: &Path) -> bool {
+    match config_value(&common_dir(gitdir).join("config"), "core", "fileMode") {
+        Some(value) => is_true(&value),
+        None => !cfg!(target_os = "windows"),
+    }
+}
+
+/// Whether each worktree can have its own config in `config.worktree` (`extensions.worktreeConfig`).
+pub fn worktree_config_enabled(gitdir: &Path) -> bool {
+    config_value(
+        &common_dir(gitdir).join("config"),
+        "extensions",
+        "worktreeConfig",
+    )
+    .is_some_and(|v| is_true(&v))
+}
+
+// Root of the working tree of the worktree. A linked worktree records the path to its `.git` file in
+// `gitdir` file.
+fn worktree_root(gitdir: &Path) -> PathBuf {
+    match read_path(&gitdir.join("gitdir"), gitdir) {
+        Some(file) => file.parent().unwrap_or(&file).to_owned(),
+        None => work_tree(gitdir).to_owned(),
+    }
+}
+
+/// Directory where Git runs hooks of the worktree whose Git directory is `gitdir`. Worktrees share
+/// hooks in the common Git directory unless `core.hooksPath` is set in their `config.worktree`.
+pub fn hooks_dir(gitdir: &Path) -> PathBuf {
+    if worktree_config_enabled(gitdir) {
+        if let Some(path) = config_value(&gitdir.join("config.worktree"), "core", "hooksPath") {
+            // A relative path is relative to the working tree where hooks are run
+            return worktree_root(gitdir).join(path_from_bytes(&path));
+        }
+    }
+    common_dir(gitdir).join("hooks")
+}
+
+/// Root of the working tree where the Git directory found by `find_gitdir` is. The Git repository of
+/// a Jujutsu repository is in `.jj/repo/store/git` of the workspace.
+pub fn work_tree(gitdir: &Path) -> &Path {
+    if gitdir.ends_with(".jj/repo/store/git") {
+        if let Some(root) = gitdir.ancestors().nth(4) {
+            return root;
+        }
+    }
+    gitdir.parent().unwrap_or(gitdir)
+}
+
+/// Standard output of the Git command run in `dir`. It fails with the standard error when the command
+/// fails.
+#[allow(dead_code)] // Not used in build script
+pub fn output(dir: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let out = Command::new("git").args(args).current_dir(dir).output()?;
+    if !out.status.success() {
+        return Err(Error::Git(
+            String::from_utf8_lossy(&out.stderr).trim().to_string(),
+        ));
+    }
+    Ok(out.stdout)
+}
+
+/// Content of the file at the revision of the repository at `dir`. An empty `rev` means the index.
+/// `None` means the file does not exist there. Other failures of Git are errors.
+#[allow(dead_code)] // Not used in build script
+pub fn show(dir: &Path, rev: &str, path: &str) -> Result<Option<String>> {
+    let object = format!("{}:{}", rev, path);
+    match output(dir, &["cat-file", "-e", &object]) {
+        Ok(_) => {}
+        Err(Error::Git(_)) => return Ok(None),
+        Err(err) => return Err(err),
+    }
+    let out = output(dir, &["show", &object])?;
+    Ok(Some(String::from_utf8_lossy(&out).into_owned()))
+}
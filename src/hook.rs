@@ -0,0 +1,425 @@
+use config::Config;
+use error::Result;
+use script::shebang;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+pub enum HookState {
+    // No hook script is put yet
+    Missing,
+    // The hook was generated by the same version of cargo-husky
+    UpToDate,
+    // The hook was generated by an older version of cargo-husky, or it could not be read
+    Outdated,
+    // The hook was generated by a newer version of cargo-husky. It must not be downgraded
+    Newer,
+    // Someone else had already put another hook script
+    Foreign,
+}
+
+pub fn hook_state(hook: &Path) -> HookState {
+    let f = match File::open(hook) {
+        Ok(f) => f,
+        Err(..) => return HookState::Missing,
+    };
+
+    let ver_line = match io::BufReader::new(f).lines().nth(2) {
+        None => return HookState::Foreign, // Less than 2 lines. The hook script seemed to be generated by someone else
+        Some(Err(..)) => return HookState::Outdated, // Failed to read entry. Re-generate anyway
+        Some(Ok(line)) => line,
+    };
+
+    if !ver_line.contains("This hook was set by cargo-husky") {
+        return HookState::Foreign;
+    }
+
+    let ver_comment = format!(
+        "This hook was set by cargo-husky v{}",
+        env!("CARGO_PKG_VERSION")
+    );
+    if ver_line.contains(&ver_comment) {
+        return HookState::UpToDate;
+    }
+    let version = ver_line
+        .split("cargo-husky v")
+        .nth(1)
+        .and_then(|s| s.split(':').next())
+        .and_then(parse_version);
+    match (version, parse_version(env!("CARGO_PKG_VERSION"))) {
+        (Some(theirs), Some(ours)) if theirs > ours => HookState::Newer,
+        _ => HookState::Outdated,
+    }
+}
+
+/// Prefix of the header line which records the hash of the configuration the hook was generated with.
+pub const CONFIG_HASH_HEADER: &str = "# Configuration hash: ";
+
+/// Information recorded in the header of a hook script generated by cargo-husky.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManagedHookInfo {
+    /// Version of cargo-husky which generated the script
+    pub version: String,
+    /// Hash of the configuration (enabled features and package metadata) which the script was
+    /// generated with. `None` when the script does not record it (e.g. user hook scripts)
+    pub config_hash: Option<String>,
+}
+
+/// Check whether the script at `path` was generated by cargo-husky and return the information in its
+/// header. `None` is returned for scripts put by others. It fails when the file cannot be read.
+pub fn is_managed_hook(path: &Path) -> Result<Option<ManagedHookInfo>> {
+    let mut lines = io::BufReader::new(File::open(path)?).lines();
+    // Scripts which are not text are not generated by cargo-husky
+    let ver_line = match lines.nth(2) {
+        Some(Ok(line)) => line,
+        _ => return Ok(None),
+    };
+    let version = match ver_line.split_once("This hook was set by cargo-husky v") {
+        Some((_, rest)) => rest.split(':').next().unwrap_or(rest).to_string(),
+        None => return Ok(None),
+    };
+    // The header is the comment lines at the top of the script
+    let config_hash = lines
+        .map_while(|l| l.ok().filter(|l| l.starts_with('#')))
+        .find_map(|l| l.strip_prefix(CONFIG_HASH_HEADER).map(str::to_string));
+    Ok(Some(ManagedHookInfo {
+        version,
+        config_hash,
+    }))
+}
+
+// Major, minor and patch versions. Pre-release and build metadata are ignored.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut nums = version
+        .split(['-', '+'])
+        .next()?
+        .split('.')
+        .map(|n| n.trim().parse::<u64>().ok());
+    let major = nums.next()??;
+    let minor = nums.next()??;
+    let patch = nums.next()??;
+    Some((major, minor, patch))
+}
+
+/// What installing the hook script generated by cargo-husky does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookAction {
+    /// Put a new hook script
+    Create,
+    /// Replace the hook script generated by another version of cargo-husky. It is backed up before
+    Overwrite,
+    /// Rename the hook script put by someone else to `{hook}.local` and run it from the new hook
+    /// script (chain-hooks feature)
+    Chain,
+    /// Keep the hook script generated by the same version. It is only rewritten when its content
+    /// differs from the newly generated script
+    Keep,
+    /// Skip the hook since someone else had already put another hook script
+    SkipForeign,
+    /// Skip the hook since it was generated by a newer version of cargo-husky
+    SkipNewer,
+    /// Skip the hook since `{hook}.local` already exists and the existing hook cannot be chained
+    SkipChainConflict,
+}
+
+impl fmt::Display for HookAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let desc = match self {
+            HookAction::Create => "create",
+            HookAction::Overwrite => "overwrite (the old hook is backed up)",
+            HookAction::Chain => "chain the existing hook",
+            HookAction::Keep => "keep (up-to-date)",
+            HookAction::SkipForeign => "skip (another hook script is already there)",
+            HookAction::SkipNewer => "skip (generated by a newer version of cargo-husky)",
+            HookAction::SkipChainConflict => "skip (the existing hook cannot be chained)",
+        };
+        f.write_str(desc)
+    }
+}
+
+pub fn hook_action(hook_path: &Path, chain: bool) -> HookAction {
+    match hook_state(hook_path) {
+        HookState::Missing => HookAction::Create,
+        HookState::Outdated => HookAction::Overwrite,
+        HookState::UpToDate => HookAction::Keep,
+        HookState::Newer => HookAction::SkipNewer,
+        HookState::Foreign if !chain => HookAction::SkipForeign,
+        HookState::Foreign if chained_hook_path(hook_path).exists() => {
+            HookAction::SkipChainConflict
+        }
+        HookState::Foreign => HookAction::Chain,
+    }
+}
+
+// All hooks which can be generated by features
+const GENERATABLE_HOOKS: &[&str] = &[
+    "pre-push",
+    "pre-commit",
+    "post-merge",
+    "prepare-commit-msg",
+    "post-commit",
+    "pre-rebase",
+    "post-checkout",
+    "post-rewrite",
+    "applypatch-msg",
+    "pre-applypatch",
+];
+
+// Hooks generated by features
+pub fn generated_hooks() -> Vec<&'static str> {
+    let mut hooks = vec![];
+    if cfg!(feature = "prepush-hook") {
+        hooks.push("pre-push");
+    }
+    if cfg!(feature = "precommit-hook") {
+        hooks.push("pre-commit");
+    }
+    if cfg!(feature = "postmerge-hook") {
+        hooks.push("post-merge");
+    }
+    if cfg!(feature = "preparecommitmsg-hook") {
+        hooks.push("prepare-commit-msg");
+    }
+    if cfg!(feature = "postcommit-hook") {
+        hooks.push("post-commit");
+    }
+    if cfg!(feature = "prerebase-hook") {
+        hooks.push("pre-rebase");
+    }
+    if cfg!(feature = "postcheckout-hook") {
+        hooks.push("post-checkout");
+    }
+    if cfg!(feature = "postrewrite-hook") {
+        hooks.push("post-rewrite");
+    }
+    if cfg!(feature = "applypatchmsg-hook") {
+        hooks.push("applypatch-msg");
+    }
+    if cfg!(feature = "preapplypatch-hook") {
+        hooks.push("pre-applypatch");
+    }
+    hooks
+}
+
+// Hooks in `hooks_dir` which were generated by cargo-husky for features no longer enabled. User
+// hooks and hooks generated by a newer version are not included.
+pub fn stale_hooks(hooks_dir: &Path, generated: &[&str]) -> Vec<&'static str> {
+    GENERATABLE_HOOKS
+        .iter()
+        .filter(|hook| !generated.contains(hook))
+        .filter(|hook| {
+            let path = hooks_dir.join(hook);
+            is_generated_hook(&path) || is_generated_hook(&merged_hook_path(&path))
+        })
+        .cloned()
+        .collect()
+}
+
+// Only generated hooks put the build script at line 4. User hooks have their own content there
+pub fn is_generated_hook(path: &Path) -> bool {
+    if !matches!(hook_state(path), HookState::UpToDate | HookState::Outdated) {
+        return false;
+    }
+    fs::read_to_string(path)
+        .map(|s| matches!(s.lines().nth(3), Some(l) if l.starts_with("# Generated by script ")))
+        .unwrap_or(false)
+}
+
+/// What the build script would do for each hook generated by enabled features, without touching
+/// `hooks_dir`. User hooks are not included.
+pub fn install_actions(hooks_dir: &Path) -> Vec<(&'static str, HookAction)> {
+    if cfg!(feature = "user-hooks") && !cfg!(feature = "combine-hooks") {
+        return vec![];
+    }
+    generated_hooks()
+        .into_iter()
+        .map(|hook| {
+            let action = hook_action(&hooks_dir.join(hook), cfg!(feature = "chain-hooks"));
+            (hook, action)
+        })
+        .collect()
+}
+
+// Lines enclosing the block which cargo-husky manages in hook scripts of other hook managers
+pub const MANAGED_BLOCK_BEGIN: &str = "# >>> cargo-husky >>>";
+pub const MANAGED_BLOCK_END: &str = "# <<< cargo-husky <<<";
+
+// Line indices of the first and the last lines of the managed block
+fn managed_block_range(lines: &[&str]) -> Option<(usize, usize)> {
+    let begin = lines
+        .iter()
+        .position(|l| l.trim_end() == MANAGED_BLOCK_BEGIN)?;
+    let end = lines[begin..]
+        .iter()
+        .position(|l| l.trim_end() == MANAGED_BLOCK_END)?;
+    Some((begin, begin + end))
+}
+
+fn join_lines(lines: &[&str]) -> String {
+    lines.iter().map(|l| format!("{}\n", l)).collect()
+}
+
+// Put the managed block in the hook script. The existing block is replaced in place. Otherwise the
+// block is appended to the script. A new script with the shebang configured by `shebang` is created
+// when `script` is `None`.
+pub fn put_managed_block(script: Option<&str>, block: &str, config: &Config) -> Result<String> {
+    let script = match script {
+        Some(s) => s,
+        None => return Ok(format!("{}\n\n{}", shebang(config)?, block)),
+    };
+    let lines = script.lines().collect::<Vec<_>>();
+    Ok(match managed_block_range(&lines) {
+        Some((begin, end)) => join_lines(&lines[..begin]) + block + &join_lines(&lines[end + 1..]),
+        None if lines.is_empty() => block.to_string(),
+        None => join_lines(&lines) + "\n" + block,
+    })
+}
+
+// Remove the managed block from the hook script. `None` means the script has no managed block.
+pub fn remove_managed_block(script: &str) -> Option<String> {
+    let lines = script.lines().collect::<Vec<_>>();
+    let (begin, end) = managed_block_range(&lines)?;
+    let mut before = &lines[..begin];
+    // Blank line inserted before the appended block
+    if matches!(before.last(), Some(l) if l.trim().is_empty()) {
+        before = &before[..before.len() - 1];
+    }
+    Some(join_lines(before) + &join_lines(&lines[end + 1..]))
+}
+
+// Hooks which receive their input from stdin. The input must be forwarded to a chained hook.
+pub fn hook_reads_stdin(hook: &str) -> bool {
+    matches!(hook, "pre-push" | "post-rewrite")
+}
+
+pub fn chained_hook_path(hook_path: &Path) -> PathBuf {
+    let mut name = hook_path.file_name().unwrap().to_os_string();
+    name.push(".local");
+    hook_path.with_file_name(name)
+}
+
+// Path of the hook script run from the managed block when it is merged into the hook script of
+// another hook manager
+pub fn merged_hook_path(hook_path: &Path) -> PathBuf {
+    let mut name = hook_path.file_name().unwrap().to_os_string();
+    name.push(".cargo-husky");
+    hook_path.with_file_name(name)
+}
+
+// Configuration files of lefthook in the repository root
+const LEFTHOOK_CONFIGS: &[&str] = &[
+    "lefthook.yml",
+    "lefthook.yaml",
+    "lefthook.toml",
+    "lefthook.json",
+    ".lefthook.yml",
+    ".lefthook.yaml",
+    ".lefthook.toml",
+    ".lefthook.json",
+];
+
+// Name of another hook manager which manages the hook. It is detected from the existing hook script
+// or configuration files in the repository root `root`. Hooks generated by cargo-husky are not
+// managed by others.
+pub fn hook_manager(root: &Path, hook_path: &Path) -> Option<&'static str> {
+    match hook_state(hook_path) {
+        HookState::Foreign => {
+            let script = fs::read_to_string(hook_path).unwrap_or_default();
+            if script.contains("lefthook") {
+                return Some("lefthook");
+            }
+            if script.contains("File generated by pre-commit") {
+                return Some("pre-commit");
+            }
+            if script.contains("overcommit") {
+                return Some("overcommit");
+            }
+        }
+        HookState::Missing => {}
+        _ => return None,
+    }
+    if LEFTHOOK_CONFIGS.iter().any(|f| root.join(f).is_file()) {
+        Some("lefthook")
+    } else {
+        None
+    }
+}
+
+/// Permissions of installed hook scripts unless `hook-mode` is configured. The umask of the process
+/// is applied to them.
+pub const DEFAULT_HOOK_MODE: u32 = 0o755;
+
+/// Permission bits of the file. `None` on Windows where files have no permission bits.
+#[cfg(target_os = "windows")]
+pub fn permission_bits(_path: &Path) -> Option<u32> {
+    None
+}
+
+/// Permission bits of the file. `None` on Windows where files have no permission bits.
+#[cfg(not(target_os = "windows"))]
+pub fn permission_bits(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path)
+        .ok()
+        .map(|m| m.permissions().mode() & 0o777)
+}
+
+/// Write the executable file atomically. The content is written to a temporary file in the same
+/// directory and it is renamed to the path so that a truncated hook is never left even if the
+/// build is interrupted.
+pub fn write_executable_file(path: &Path, content: &[u8], mode: u32) -> io::Result<()> {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap());
+    name.push(format!(".{}.tmp", process::id()));
+    let tmp = path.with_file_name(name);
+    let written = create_executable_file(&tmp, mode)
+        .and_then(|mut f| {
+            f.write_all(content)?;
+            f.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
+
+/// Write the file unless it already has the content. Identical file is not rewritten to avoid
+/// changing its mtime. Parent directories are created.
+pub fn write_file_if_changed(path: &Path, content: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    if fs::read_to_string(path).ok().as_deref() != Some(content) {
+        fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+pub fn create_executable_file(path: &Path, _mode: u32) -> io::Result<File> {
+    File::create(path)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn create_executable_file(path: &Path, mode: u32) -> io::Result<File> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(mode)
+        .open(path)?;
+    // The umask is applied to the mode. Git silently ignores the hook when umask such as 0177 drops
+    // the bits to read and run it
+    let perms = file.metadata()?.permissions().mode();
+    if perms & 0o500 != 0o500 {
+        file.set_permissions(fs::Permissions::from_mode(perms | 0o500))?;
+    }
+    Ok(file)
+}
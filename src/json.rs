@@ -0,0 +1,212 @@
+// Minimal JSON parser to read output of `cargo metadata` without depending on any crate. Numbers
+// are kept as raw text since they are not used.
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Object),
+}
+
+pub type Object = BTreeMap<String, Value>;
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(elems) => Some(elems),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(o) => o.get(key),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse(src: &str) -> Result<Value, String> {
+    let mut parser = Parser {
+        chars: src.chars().collect(),
+        pos: 0,
+    };
+    let value = parser.parse_value().and_then(|v| {
+        parser.skip_whitespaces();
+        match parser.peek() {
+            None => Ok(v),
+            Some(c) => Err(format!("Unexpected character '{}'", c)),
+        }
+    });
+    value.map_err(|msg| format!("{} at offset {}", msg, parser.pos))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+type ParseResult<T> = Result<T, String>;
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> ParseResult<()> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!("Expected '{}'", c))
+        }
+    }
+
+    fn skip_whitespaces(&mut self) {
+        while let Some(' ') | Some('\t') | Some('\n') | Some('\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn parse_value(&mut self) -> ParseResult<Value> {
+        self.skip_whitespaces();
+        match self.peek() {
+            Some('"') => self.parse_string().map(Value::String),
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_object(),
+            Some(_) => self.parse_literal(),
+            None => Err("Expected a value".to_string()),
+        }
+    }
+
+    fn parse_hex4(&mut self) -> ParseResult<u32> {
+        if self.pos + 4 > self.chars.len() {
+            return Err("Invalid unicode escape".to_string());
+        }
+        let hex: String = self.chars[self.pos..self.pos + 4].iter().collect();
+        self.pos += 4;
+        u32::from_str_radix(&hex, 16).map_err(|_| "Invalid unicode escape".to_string())
+    }
+
+    fn parse_string(&mut self) -> ParseResult<String> {
+        self.expect('"')?;
+        let mut buf = String::new();
+        loop {
+            let c = self.peek().ok_or("Unterminated string")?;
+            self.pos += 1;
+            match c {
+                '"' => return Ok(buf),
+                '\\' => {
+                    let c = self.peek().ok_or("Unterminated string")?;
+                    self.pos += 1;
+                    match c {
+                        '"' | '\\' | '/' => buf.push(c),
+                        'b' => buf.push('\u{8}'),
+                        'f' => buf.push('\u{c}'),
+                        'n' => buf.push('\n'),
+                        'r' => buf.push('\r'),
+                        't' => buf.push('\t'),
+                        'u' => {
+                            let mut code = self.parse_hex4()?;
+                            // Characters outside BMP are encoded as surrogate pair. A surrogate
+                            // without its pair is replaced with U+FFFD
+                            if (0xd800..0xdc00).contains(&code)
+                                && self.chars[self.pos..].starts_with(&['\\', 'u'])
+                            {
+                                self.pos += 2;
+                                let low = self.parse_hex4()?;
+                                if (0xdc00..0xe000).contains(&low) {
+                                    code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                                } else {
+                                    buf.push('\u{fffd}');
+                                    code = low;
+                                }
+                            }
+                            buf.push(::std::char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        c => return Err(format!("Invalid escape sequence '\\{}'", c)),
+                    }
+                }
+                c => buf.push(c),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> ParseResult<Value> {
+        self.expect('[')?;
+        let mut elems = vec![];
+        self.skip_whitespaces();
+        if self.eat(']') {
+            return Ok(Value::Array(elems));
+        }
+        loop {
+            elems.push(self.parse_value()?);
+            self.skip_whitespaces();
+            if self.eat(']') {
+                return Ok(Value::Array(elems));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn parse_object(&mut self) -> ParseResult<Value> {
+        self.expect('{')?;
+        let mut object = Object::new();
+        self.skip_whitespaces();
+        if self.eat('}') {
+            return Ok(Value::Object(object));
+        }
+        loop {
+            self.skip_whitespaces();
+            let key = self.parse_string()?;
+            self.skip_whitespaces();
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            object.insert(key, value);
+            self.skip_whitespaces();
+            if self.eat('}') {
+                return Ok(Value::Object(object));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    // null, booleans and numbers
+    fn parse_literal(&mut self) -> ParseResult<Value> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.')) {
+                break;
+            }
+            self.pos += 1;
+        }
+        let raw = self.chars[start..self.pos].iter().collect::<String>();
+        match raw.as_str() {
+            "null" => Ok(Value::Null),
+            "true" => Ok(Value::Boolean(true)),
+            "false" => Ok(Value::Boolean(false)),
+            _ if raw.parse::<f64>().is_ok() => Ok(Value::Number(raw)),
+            _ => Err(format!("Unexpected value '{}'", raw)),
+        }
+    }
+}
@@ -1 +1,50 @@
+//! cargo-husky installs Git hooks with its build script on `cargo test`. This library provides
+//! functions to manage the hooks installed by cargo-husky. They are also available from `cargo husky`
+//! command.
 
+// Modules are shared with build.rs. Some items are only used by the build script.
+mod advise;
+mod affected;
+mod backup;
+#[allow(dead_code)]
+mod config;
+mod denylist;
+mod error;
+mod export;
+mod git;
+#[allow(dead_code)]
+mod hook;
+mod json;
+mod lockfile;
+mod plan;
+mod runner;
+#[allow(dead_code)]
+mod script;
+mod secrets;
+#[allow(dead_code)]
+mod toml;
+#[allow(dead_code)]
+mod verify;
+mod version_tag;
+
+pub use advise::install_advised_hooks;
+pub use affected::affected_packages;
+pub use backup::{backup_dir, backup_hook, backups, restore_hook};
+pub use denylist::{check_staged as check_staged_denylist, denied_files, glob_match, DeniedFile};
+pub use error::{Error, Result};
+pub use export::render_pre_commit_config;
+pub use git::{
+    common_dir, file_mode_enabled, find_gitdir, find_work_tree, hooks_dir, work_tree,
+    worktree_config_enabled,
+};
+pub use hook::{install_actions, is_managed_hook, HookAction, ManagedHookInfo};
+pub use lockfile::{check_staged as check_staged_lockfiles, manifest_changes_lock, StaleLockfile};
+pub use plan::{plan_path, Plan, Step};
+pub use runner::{run_hook, timings_log_path};
+pub use secrets::{
+    detect_secret, scan_diff, scan_staged, SecretFinding, ALLOW_COMMENT as SECRETS_ALLOW_COMMENT,
+};
+pub use verify::{verify_hooks, HookDrift};
+pub use version_tag::{
+    changelog_has_entry, check_pushed_tags, manifest_version, tag_version, TagMismatch,
+};
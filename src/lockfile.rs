@@ -0,0 +1,117 @@
+use error::Result;
+use git::{self, show};
+use std::path::Path;
+use toml::{self, Table, Value};
+
+/// Manifest whose staged changes require updating the lockfile which is not staged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleLockfile {
+    pub manifest: String,
+    pub lockfile: String,
+}
+
+// Tables of dependencies. They are also put in `[target.<cfg>]` tables.
+const DEPENDENCY_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+// Entries of the manifest recorded in Cargo.lock, with their paths such as `target.<cfg>.dependencies`
+fn locked_entries(manifest: &Table) -> Vec<(String, &Value)> {
+    let mut entries = vec![];
+    for path in &[
+        &["package", "name"][..],
+        &["package", "version"],
+        &["workspace", "members"],
+        &["workspace", "exclude"],
+        &["workspace", "dependencies"],
+        &["patch"],
+        &["replace"],
+    ] {
+        if let Some(value) = toml::lookup(manifest, path) {
+            entries.push((path.join("."), value));
+        }
+    }
+    for table in DEPENDENCY_TABLES {
+        if let Some(value) = manifest.get(*table) {
+            entries.push((table.to_string(), value));
+        }
+    }
+    if let Some(Value::Table(targets)) = manifest.get("target") {
+        for (cfg, target) in targets {
+            if let Value::Table(target) = target {
+                for table in DEPENDENCY_TABLES {
+                    if let Some(value) = target.get(*table) {
+                        entries.push((format!("target.{}.{}", cfg, table), value));
+                    }
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// Whether the change of the manifest from `old` to `new` requires updating Cargo.lock. Changes
+/// which don't affect the lockfile such as formatting or `[features]` are ignored. A manifest which
+/// cannot be parsed is regarded as empty.
+pub fn manifest_changes_lock(old: &str, new: &str) -> bool {
+    let old = toml::parse(old).unwrap_or_default();
+    let new = toml::parse(new).unwrap_or_default();
+    locked_entries(&old) != locked_entries(&new)
+}
+
+// Cargo.lock committed for the manifest, searched from the directory of the manifest to the
+// repository root. `None` means the lockfile is not committed.
+fn committed_lockfile(dir: &Path, manifest: &str) -> Option<String> {
+    let mut parent = Path::new(manifest).parent();
+    while let Some(p) = parent {
+        let lockfile = p.join("Cargo.lock").to_string_lossy().replace('\\', "/");
+        if git::output(dir, &["cat-file", "-e", &format!(":{}", lockfile)]).is_ok() {
+            return Some(lockfile);
+        }
+        parent = p.parent();
+    }
+    None
+}
+
+/// Check staged manifests of the repository at `dir`. Manifests whose dependencies were changed
+/// without staging the committed Cargo.lock are returned.
+pub fn check_staged(dir: &Path) -> Result<Vec<StaleLockfile>> {
+    let root = git::output(dir, &["rev-parse", "--show-toplevel"])?;
+    let root = String::from_utf8_lossy(&root);
+    let root = Path::new(root.trim_end());
+    let staged = git::output(
+        root,
+        &[
+            "-c",
+            "core.quotePath=false",
+            "diff",
+            "--cached",
+            "--name-only",
+            "--no-relative",
+            "-z",
+        ],
+    )?;
+    let staged = String::from_utf8_lossy(&staged);
+    let staged = staged
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .collect::<Vec<_>>();
+    let mut stale = vec![];
+    for manifest in &staged {
+        if Path::new(manifest).file_name().and_then(|n| n.to_str()) != Some("Cargo.toml") {
+            continue;
+        }
+        let old = show(root, "HEAD", manifest)?.unwrap_or_default();
+        let new = show(root, "", manifest)?.unwrap_or_default();
+        if !manifest_changes_lock(&old, &new) {
+            continue;
+        }
+        if let Some(lockfile) = committed_lockfile(root, manifest) {
+            if !staged.contains(&lockfile.as_str()) {
+                stale.push(StaleLockfile {
+                    manifest: manifest.to_string(),
+                    lockfile,
+                });
+            }
+        }
+    }
+    Ok(stale)
+}
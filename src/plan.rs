@@ -0,0 +1,312 @@
+use error::{Error, Result};
+use hook::write_file_if_changed;
+use script::{escape_double_quoted, quote};
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{self, string_literal, Table, Value};
+
+// Bumped when the format of plan files changes incompatibly
+const PLAN_FORMAT: i64 = 1;
+
+/// A command which hook runs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Step {
+    /// Name to configure the command in `[package.metadata.husky.commands.{name}]`
+    pub name: String,
+    /// Program and its arguments
+    pub args: Vec<String>,
+    /// Environment variables set to the command
+    pub env: Vec<(String, String)>,
+    /// Crate to install when the cargo subcommand is not found
+    pub requires: Option<String>,
+    /// Command run instead when the cargo subcommand is not found
+    pub fallback: Option<Vec<String>>,
+    /// Command is skipped with a warning when it is not found
+    pub optional: bool,
+    /// Staged Rust files are passed as arguments. The step is skipped when no file is staged
+    pub staged: bool,
+    /// Pathspec of the staged files passed with `staged` instead of Rust files
+    pub pathspec: Vec<String>,
+    /// Failure of the command only shows a warning
+    pub allow_failure: bool,
+    /// Seconds until the command is killed
+    pub timeout: Option<u64>,
+    /// Command accepts `-p {package}` to check only affected packages
+    pub per_package: bool,
+    /// Command is skipped while rebase, merge or cherry-pick is in progress
+    pub skip_in_progress: bool,
+    /// Command is run only when tags or release branches are pushed
+    pub release_only: bool,
+}
+
+impl Step {
+    pub fn new<S: AsRef<str>>(args: &[S]) -> Step {
+        Step {
+            name: args
+                .first()
+                .map(|a| a.as_ref().to_string())
+                .unwrap_or_default(),
+            args: args.iter().map(|a| a.as_ref().to_string()).collect(),
+            ..Step::default()
+        }
+    }
+
+    /// Command line shown to users. It can be run by shell.
+    pub fn display(&self) -> String {
+        let mut words = self
+            .env
+            .iter()
+            .map(|(k, v)| format!("{}=\"{}\"", k, escape_double_quoted(v)))
+            .collect::<Vec<_>>();
+        words.extend(self.args.iter().map(|a| quote(a)));
+        words.join(" ")
+    }
+
+    /// Arguments to check the cargo subcommand is installed. Toolchain such as `+nightly` is
+    /// included. Only the program is returned when it is not cargo.
+    pub fn subcommand(&self) -> &[String] {
+        if self.args[0] != "cargo" {
+            return &self.args[..1];
+        }
+        let end = self
+            .args
+            .iter()
+            .skip(1)
+            .position(|a| !a.starts_with('+'))
+            .map(|i| i + 2)
+            .unwrap_or(self.args.len());
+        &self.args[..end]
+    }
+
+    /// Pathspec of staged files passed to the command.
+    pub fn staged_pathspec(&self) -> Vec<&str> {
+        if self.pathspec.is_empty() {
+            vec!["*.rs"]
+        } else {
+            self.pathspec.iter().map(String::as_str).collect()
+        }
+    }
+}
+
+/// Commands run by `cargo husky run <hook>`. The build script writes the plan of each hook in
+/// `.git/hooks/cargo-husky/{hook}.toml`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    /// Number of commands run in parallel
+    pub jobs: usize,
+    /// Stop running commands at the first failure
+    pub fail_fast: bool,
+    /// Output of commands is only shown on failure
+    pub quiet: bool,
+    /// Show time taken by each command
+    pub timings: bool,
+    /// Append time taken by each command to `.git/cargo-husky-timings.log`
+    pub timings_log: bool,
+    /// Check only workspace members affected by the changes
+    pub affected_only: bool,
+    pub steps: Vec<Step>,
+}
+
+pub fn plan_path(hooks_dir: &Path, hook: &str) -> PathBuf {
+    hooks_dir.join("cargo-husky").join(format!("{}.toml", hook))
+}
+
+fn str_array(args: &[String]) -> String {
+    let elems = args
+        .iter()
+        .map(|a| string_literal(a))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("[{}]", elems)
+}
+
+fn get_str_array(table: &Table, key: &str) -> std::result::Result<Option<Vec<String>>, String> {
+    let elems = match table.get(key) {
+        None => return Ok(None),
+        Some(Value::Array(elems)) => elems,
+        Some(_) => return Err(format!("'{}' must be an array of strings", key)),
+    };
+    elems
+        .iter()
+        .map(|e| match e {
+            Value::String(s) => Ok(s.clone()),
+            _ => Err(format!("'{}' must be an array of strings", key)),
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+fn get_bool(table: &Table, key: &str) -> std::result::Result<bool, String> {
+    match table.get(key) {
+        None => Ok(false),
+        Some(Value::Boolean(b)) => Ok(*b),
+        Some(_) => Err(format!("'{}' must be a boolean", key)),
+    }
+}
+
+fn parse_step(table: &Table) -> std::result::Result<Step, String> {
+    let args = match get_str_array(table, "args")? {
+        Some(args) if !args.is_empty() => args,
+        _ => return Err("'args' of step must not be empty".to_string()),
+    };
+    let mut env = vec![];
+    match table.get("env") {
+        None => {}
+        Some(Value::Table(t)) => {
+            for (k, v) in t {
+                match v {
+                    Value::String(v) => env.push((k.clone(), v.clone())),
+                    _ => return Err(format!("Value of env var '{}' must be string", k)),
+                }
+            }
+        }
+        Some(_) => return Err("'env' must be a table".to_string()),
+    }
+    let requires = match table.get("requires") {
+        None => None,
+        Some(Value::String(s)) => Some(s.clone()),
+        Some(_) => return Err("'requires' must be a string".to_string()),
+    };
+    let name = match table.get("name") {
+        None => args[0].clone(),
+        Some(Value::String(s)) => s.clone(),
+        Some(_) => return Err("'name' must be a string".to_string()),
+    };
+    let timeout = match table.get("timeout") {
+        None => None,
+        Some(Value::Integer(i)) if *i > 0 => Some(*i as u64),
+        Some(_) => return Err("'timeout' must be a positive integer".to_string()),
+    };
+    Ok(Step {
+        name,
+        args,
+        env,
+        requires,
+        fallback: get_str_array(table, "fallback")?,
+        optional: get_bool(table, "optional")?,
+        staged: get_bool(table, "staged")?,
+        pathspec: get_str_array(table, "pathspec")?.unwrap_or_default(),
+        allow_failure: get_bool(table, "allow-failure")?,
+        timeout,
+        per_package: get_bool(table, "per-package")?,
+        skip_in_progress: get_bool(table, "skip-in-progress")?,
+        release_only: get_bool(table, "release-only")?,
+    })
+}
+
+impl Plan {
+    /// Render the plan as TOML.
+    pub fn render(&self, hook: &str) -> String {
+        let mut s = format!(
+            "# Commands run by {} hook. Generated by cargo-husky v{}. Do not edit\nformat = {}\njobs = {}\nfail-fast = {}\nquiet = {}\ntimings = {}\ntimings-log = {}\naffected-only = {}\n",
+            hook,
+            env!("CARGO_PKG_VERSION"),
+            PLAN_FORMAT,
+            self.jobs.max(1),
+            self.fail_fast,
+            self.quiet,
+            self.timings,
+            self.timings_log,
+            self.affected_only
+        );
+        for step in &self.steps {
+            s += "\n[[steps]]\n";
+            s += &format!("name = {}\n", string_literal(&step.name));
+            s += &format!("args = {}\n", str_array(&step.args));
+            if !step.env.is_empty() {
+                let env = step
+                    .env
+                    .iter()
+                    .map(|(k, v)| format!("{} = {}", string_literal(k), string_literal(v)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                s += &format!("env = {{ {} }}\n", env);
+            }
+            if let Some(krate) = &step.requires {
+                s += &format!("requires = {}\n", string_literal(krate));
+            }
+            if let Some(args) = &step.fallback {
+                s += &format!("fallback = {}\n", str_array(args));
+            }
+            if step.optional {
+                s += "optional = true\n";
+            }
+            if step.staged {
+                s += "staged = true\n";
+            }
+            if !step.pathspec.is_empty() {
+                s += &format!("pathspec = {}\n", str_array(&step.pathspec));
+            }
+            if step.allow_failure {
+                s += "allow-failure = true\n";
+            }
+            if let Some(secs) = step.timeout {
+                s += &format!("timeout = {}\n", secs);
+            }
+            if step.per_package {
+                s += "per-package = true\n";
+            }
+            if step.skip_in_progress {
+                s += "skip-in-progress = true\n";
+            }
+            if step.release_only {
+                s += "release-only = true\n";
+            }
+        }
+        s
+    }
+
+    pub fn parse(src: &str) -> std::result::Result<Plan, String> {
+        let root = toml::parse(src)?;
+        match root.get("format") {
+            Some(Value::Integer(PLAN_FORMAT)) => {}
+            _ => return Err("Unsupported format. Please update cargo-husky command".to_string()),
+        }
+        let jobs = match root.get("jobs") {
+            None => 1,
+            Some(Value::Integer(i)) if *i > 0 => *i as usize,
+            Some(_) => return Err("'jobs' must be a positive integer".to_string()),
+        };
+        let fail_fast = match root.get("fail-fast") {
+            None => true,
+            Some(Value::Boolean(b)) => *b,
+            Some(_) => return Err("'fail-fast' must be a boolean".to_string()),
+        };
+        let quiet = get_bool(&root, "quiet")?;
+        let timings = get_bool(&root, "timings")?;
+        let timings_log = get_bool(&root, "timings-log")?;
+        let affected_only = get_bool(&root, "affected-only")?;
+        let mut steps = vec![];
+        match root.get("steps") {
+            None => {}
+            Some(Value::Array(elems)) => {
+                for elem in elems {
+                    match elem {
+                        Value::Table(t) => steps.push(parse_step(t)?),
+                        _ => return Err("'steps' must be an array of tables".to_string()),
+                    }
+                }
+            }
+            Some(_) => return Err("'steps' must be an array of tables".to_string()),
+        }
+        Ok(Plan {
+            jobs,
+            fail_fast,
+            quiet,
+            timings,
+            timings_log,
+            affected_only,
+            steps,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Plan> {
+        let src = fs::read_to_string(path)
+            .map_err(|e| Error::InvalidPlan(path.to_owned(), e.to_string()))?;
+        Plan::parse(&src).map_err(|msg| Error::InvalidPlan(path.to_owned(), msg))
+    }
+
+    pub fn save(&self, path: &Path, hook: &str) -> Result<()> {
+        Ok(write_file_if_changed(path, &self.render(hook))?)
+    }
+}
@@ -0,0 +1,692 @@
+use affected::affected_by_hook;
+use error::Result;
+use git::{self, path_from_bytes};
+use plan::{plan_path, Plan, Step};
+use std::collections::VecDeque;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+fn command(args: &[String], env: &[(String, String)]) -> Command {
+    let mut cmd = Command::new(&args[0]);
+    cmd.args(&args[1..]);
+    for (k, v) in env {
+        cmd.env(k, v);
+    }
+    cmd
+}
+
+fn is_installed(step: &Step) -> bool {
+    command(step.subcommand(), &[])
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+// Staged files matching the pathspec relative to the current directory. Deleted files and files
+// outside the current directory (`working-dir`) are excluded.
+fn staged_files(pathspec: &[&str]) -> Result<Vec<String>> {
+    let mut args = vec![
+        "diff",
+        "--cached",
+        "--name-only",
+        "--relative",
+        "--diff-filter=ACMR",
+        "-z",
+        "--",
+    ];
+    args.extend_from_slice(pathspec);
+    let out = git::output(Path::new("."), &args)?;
+    Ok(String::from_utf8_lossy(&out)
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+enum Prepared {
+    // Command to run and its command line shown to users
+    Run(Box<Command>, String),
+    // Nothing to check
+    Skip,
+    // Required cargo subcommand is not installed
+    Missing,
+}
+
+fn prepare(step: &Step, label: &str) -> Result<Prepared> {
+    let mut step = step.clone();
+    if step.requires.is_some() && !is_installed(&step) {
+        let sub = step.subcommand().join(" ");
+        match step.fallback.take() {
+            Some(fallback) => {
+                let fallback = Step::new(&fallback);
+                eprintln!(
+                    "{}cargo-husky: '{}' is not installed. Falling back to '{}'",
+                    label,
+                    sub,
+                    fallback.subcommand().join(" ")
+                );
+                step = Step {
+                    env: step.env,
+                    ..fallback
+                };
+            }
+            None if step.optional => {
+                eprintln!(
+                    "{}cargo-husky: '{}' is not installed. Skipping it. Install it by 'cargo install {}'",
+                    label,
+                    sub,
+                    step.requires.as_ref().unwrap()
+                );
+                return Ok(Prepared::Skip);
+            }
+            None => {
+                eprintln!(
+                    "{}cargo-husky: '{}' is not installed. Please install it by 'cargo install {}'",
+                    label,
+                    sub,
+                    step.requires.as_ref().unwrap()
+                );
+                return Ok(Prepared::Missing);
+            }
+        }
+    }
+    let mut cmd = command(&step.args, &step.env);
+    if !step.staged {
+        return Ok(Prepared::Run(Box::new(cmd), step.display()));
+    }
+    let files = staged_files(&step.staged_pathspec())?;
+    if files.is_empty() {
+        return Ok(Prepared::Skip);
+    }
+    cmd.args(files);
+    Ok(Prepared::Run(
+        Box::new(cmd),
+        format!("{} <staged files>", step.display()),
+    ))
+}
+
+// Exit status of commands killed by timeout, which is the same as `timeout` command
+const TIMEOUT_STATUS: i32 = 124;
+
+// Wait for the child process. It is killed when it does not finish within the timeout.
+fn wait(child: &mut Child, step: &Step, label: &str) -> Result<i32> {
+    let secs = match step.timeout {
+        Some(secs) => secs,
+        // Killed by signal
+        None => return Ok(child.wait()?.code().unwrap_or(1)),
+    };
+    let deadline = Instant::now() + Duration::from_secs(secs);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status.code().unwrap_or(1));
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            eprintln!(
+                "{}cargo-husky: '{}' timed out after {} seconds",
+                label,
+                step.display(),
+                secs
+            );
+            return Ok(TIMEOUT_STATUS);
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+// Failure of the step is ignored when it is allowed to fail
+fn check_failure(step: &Step, code: i32, label: &str) -> i32 {
+    if code != 0 && step.allow_failure {
+        eprintln!(
+            "{}cargo-husky: '{}' failed but it is allowed to fail",
+            label,
+            step.display()
+        );
+        return 0;
+    }
+    code
+}
+
+// Copy output of the command line by line with the label so that outputs of commands running in
+// parallel are not mixed within a line
+fn forward<R, W>(from: R, mut to: W, label: String)
+where
+    R: Read,
+    W: Write,
+{
+    let mut reader = BufReader::new(from);
+    let mut line = vec![];
+    while let Ok(n) = reader.read_until(b'\n', &mut line) {
+        if n == 0 {
+            break;
+        }
+        if !line.ends_with(b"\n") {
+            line.push(b'\n');
+        }
+        let mut buf = label.clone().into_bytes();
+        buf.extend_from_slice(&line);
+        let _ = to.write_all(&buf);
+        line.clear();
+    }
+}
+
+// Output of stdout and stderr captured in one buffer
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OutputMode {
+    // Commands write to the terminal directly
+    Full,
+    // Each line is prefixed with the command name. Used when running commands in parallel
+    Labeled,
+    // Output is captured and only shown when the command fails
+    Quiet,
+    // Output is captured and reported in JSON
+    Json,
+}
+
+// Colors are disabled when stdout is not a terminal or `$NO_COLOR` is set
+fn colored() -> bool {
+    io::stdout().is_terminal() && !matches!(env::var_os("NO_COLOR"), Some(v) if !v.is_empty())
+}
+
+// Decorate the text with the SGR color code
+fn paint(code: u8, text: &str) -> String {
+    if colored() {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+// Line to show the result of the command
+fn status_line(line: &str, code: i32) -> String {
+    if code == 0 {
+        paint(32, &format!("PASS: {}", line))
+    } else {
+        paint(31, &format!("FAIL: {} (exit status {})", line, code))
+    }
+}
+
+// Run the step and return its exit status and captured output
+fn run_step(step: &Step, mode: OutputMode) -> Result<(i32, Vec<u8>)> {
+    let label = match mode {
+        OutputMode::Labeled => format!("[{}] ", step.subcommand().join(" ")),
+        _ => String::new(),
+    };
+    let (mut cmd, line) = match prepare(step, &label)? {
+        Prepared::Run(cmd, line) => (cmd, line),
+        Prepared::Skip => return Ok((0, vec![])),
+        Prepared::Missing => return Ok((check_failure(step, 1, &label), vec![])),
+    };
+    if mode == OutputMode::Full {
+        println!("{}", paint(1, &format!("[cargo-husky] Running {}", line)));
+        let mut child = cmd.spawn()?;
+        let code = wait(&mut child, step, &label)?;
+        println!("{}", status_line(&line, code));
+        return Ok((check_failure(step, code, &label), vec![]));
+    }
+
+    if mode == OutputMode::Labeled {
+        println!("{}+{}", label, line);
+    }
+    let captured = Captured::default();
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let (out, err) = if mode == OutputMode::Labeled {
+        let (l1, l2) = (label.clone(), label.clone());
+        (
+            thread::spawn(move || forward(stdout, io::stdout(), l1)),
+            thread::spawn(move || forward(stderr, io::stderr(), l2)),
+        )
+    } else {
+        let (c1, c2) = (captured.clone(), captured.clone());
+        (
+            thread::spawn(move || forward(stdout, c1, String::new())),
+            thread::spawn(move || forward(stderr, c2, String::new())),
+        )
+    };
+    let code = wait(&mut child, step, &label)?;
+    let _ = out.join();
+    let _ = err.join();
+    if mode == OutputMode::Quiet {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        if code != 0 {
+            let _ = stdout.write_all(&captured.0.lock().unwrap());
+        }
+        let _ = writeln!(stdout, "{}", status_line(&line, code));
+    }
+    let output = captured.0.lock().unwrap().clone();
+    Ok((check_failure(step, code, &label), output))
+}
+
+struct StepResult {
+    status: Result<i32>,
+    elapsed: Duration,
+    output: Vec<u8>,
+}
+
+impl StepResult {
+    fn succeeded(&self) -> bool {
+        matches!(self.status, Ok(0))
+    }
+
+    fn status_name(&self) -> &'static str {
+        if self.succeeded() {
+            "ok"
+        } else {
+            "failed"
+        }
+    }
+}
+
+fn run_step_timed(step: &Step, mode: OutputMode) -> StepResult {
+    let start = Instant::now();
+    let (status, output) = match run_step(step, mode) {
+        Ok((code, output)) => (Ok(code), output),
+        Err(err) => (Err(err), vec![]),
+    };
+    StepResult {
+        status,
+        elapsed: start.elapsed(),
+        output,
+    }
+}
+
+// Run steps with `jobs` worker threads and return result of each step. With fail-fast policy,
+// steps are not started after some step failed and their results are `None`.
+fn run_parallel(
+    steps: &[Step],
+    jobs: usize,
+    fail_fast: bool,
+    mode: OutputMode,
+) -> Vec<Option<StepResult>> {
+    let queue = Arc::new(Mutex::new(
+        steps.iter().cloned().enumerate().collect::<VecDeque<_>>(),
+    ));
+    let results = Arc::new(Mutex::new(
+        (0..steps.len()).map(|_| None).collect::<Vec<_>>(),
+    ));
+    let failed = Arc::new(AtomicBool::new(false));
+    let workers = (0..jobs.min(steps.len()))
+        .map(|_| {
+            let queue = queue.clone();
+            let results = results.clone();
+            let failed = failed.clone();
+            thread::spawn(move || loop {
+                if fail_fast && failed.load(Ordering::SeqCst) {
+                    return;
+                }
+                let next = queue.lock().unwrap().pop_front();
+                let (idx, step) = match next {
+                    Some(next) => next,
+                    None => return,
+                };
+                let result = run_step_timed(&step, mode);
+                if !result.succeeded() {
+                    failed.store(true, Ordering::SeqCst);
+                }
+                results.lock().unwrap()[idx] = Some(result);
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut panicked = false;
+    for worker in workers {
+        panicked |= worker.join().is_err();
+    }
+    let mut results = results.lock().unwrap();
+    if panicked {
+        // Steps which were running in the panicked worker have no result
+        for result in results.iter_mut().filter(|r| r.is_none()) {
+            *result = Some(StepResult {
+                status: Ok(1),
+                elapsed: Duration::default(),
+                output: vec![],
+            });
+        }
+    }
+    results.drain(..).collect()
+}
+
+fn run_sequential(steps: &[Step], fail_fast: bool, mode: OutputMode) -> Vec<Option<StepResult>> {
+    let mut results: Vec<Option<StepResult>> = vec![];
+    for step in steps {
+        if fail_fast && results.iter().flatten().any(|r| !r.succeeded()) {
+            results.push(None);
+            continue;
+        }
+        results.push(Some(run_step_timed(step, mode)));
+    }
+    results
+}
+
+fn print_timings(hook: &str, steps: &[Step], results: &[Option<StepResult>]) {
+    eprintln!("cargo-husky: Timings of {} hook", hook);
+    let mut total = Duration::default();
+    for (step, result) in steps.iter().zip(results) {
+        match result {
+            Some(r) => {
+                total += r.elapsed;
+                eprintln!(
+                    "    {:>8.2}s  {:<7} {}",
+                    r.elapsed.as_secs_f64(),
+                    r.status_name(),
+                    step.display()
+                );
+            }
+            None => eprintln!("    {:>9}  {:<7} {}", "-", "skipped", step.display()),
+        }
+    }
+    eprintln!("    {:>8.2}s  total", total.as_secs_f64());
+}
+
+// Append timings to the log file. Each line is tab-separated values of Unix time, hook name,
+// status, seconds and command.
+fn log_timings(
+    path: &Path,
+    hook: &str,
+    steps: &[Step],
+    results: &[Option<StepResult>],
+) -> io::Result<()> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut lines = String::new();
+    for (step, result) in steps.iter().zip(results) {
+        if let Some(r) = result {
+            lines += &format!(
+                "{}\t{}\t{}\t{:.3}\t{}\n",
+                now,
+                hook,
+                r.status_name(),
+                r.elapsed.as_secs_f64(),
+                step.display()
+            );
+        }
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(lines.as_bytes())
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+// JSON document of results for editors and other tools
+fn results_json(hook: &str, steps: &[Step], results: &[Option<StepResult>]) -> String {
+    let commands = steps
+        .iter()
+        .zip(results)
+        .map(|(step, result)| {
+            let mut fields = vec![
+                format!("\"name\":{}", json_string(&step.name)),
+                format!("\"command\":{}", json_string(&step.display())),
+            ];
+            match result {
+                Some(r) => {
+                    fields.push(format!("\"status\":\"{}\"", r.status_name()));
+                    match &r.status {
+                        Ok(code) => fields.push(format!("\"exit_code\":{}", code)),
+                        Err(err) => {
+                            fields.push(format!("\"error\":{}", json_string(&err.to_string())))
+                        }
+                    }
+                    fields.push(format!("\"duration\":{:.3}", r.elapsed.as_secs_f64()));
+                    fields.push(format!(
+                        "\"output\":{}",
+                        json_string(&String::from_utf8_lossy(&r.output))
+                    ));
+                }
+                None => fields.push("\"status\":\"skipped\"".to_string()),
+            }
+            format!("{{{}}}", fields.join(","))
+        })
+        .collect::<Vec<_>>();
+    let success = results.iter().flatten().all(StepResult::succeeded);
+    format!(
+        "{{\"hook\":{},\"success\":{},\"commands\":[{}]}}",
+        json_string(hook),
+        success,
+        commands.join(",")
+    )
+}
+
+// Replace `--all` or `--workspace` with `-p` options of the packages. Options after `--` are passed
+// to the subcommand.
+fn for_packages(args: &[String], packages: &[String]) -> Vec<String> {
+    let end = args.iter().position(|a| a == "--").unwrap_or(args.len());
+    let mut replaced = args[..end]
+        .iter()
+        .filter(|a| *a != "--all" && *a != "--workspace")
+        .cloned()
+        .collect::<Vec<_>>();
+    for pkg in packages {
+        replaced.push("-p".to_string());
+        replaced.push(pkg.clone());
+    }
+    replaced.extend_from_slice(&args[end..]);
+    replaced
+}
+
+// Narrow down steps to workspace members affected by changes of the hook. When the affected
+// members cannot be determined, the steps are run as-is.
+fn affected_steps(hook: &str, steps: Vec<Step>) -> Vec<Step> {
+    let packages = match affected_by_hook(hook) {
+        Ok(Some(packages)) => packages,
+        Ok(None) => return steps,
+        Err(err) => {
+            eprintln!("cargo-husky: {}. Checking all packages", err);
+            return steps;
+        }
+    };
+    if packages.is_empty() {
+        eprintln!("cargo-husky: No package is affected by the changes");
+    } else {
+        eprintln!("cargo-husky: Affected packages: {}", packages.join(", "));
+    }
+    steps
+        .into_iter()
+        .filter_map(|mut step| {
+            if !step.per_package {
+                return Some(step);
+            }
+            if packages.is_empty() {
+                eprintln!("cargo-husky: Skipping '{}'", step.display());
+                return None;
+            }
+            step.args = for_packages(&step.args, &packages);
+            step.fallback = step.fallback.map(|args| for_packages(&args, &packages));
+            Some(step)
+        })
+        .collect()
+}
+
+// Rebase, merge or cherry-pick in progress in the repository
+fn in_progress() -> Result<Option<&'static str>> {
+    let out = git::output(Path::new("."), &["rev-parse", "--git-dir"])?;
+    let git_dir = path_from_bytes(out.trim_ascii_end());
+    let state = if git_dir.join("rebase-merge").is_dir() || git_dir.join("rebase-apply").is_dir() {
+        Some("rebase")
+    } else if git_dir.join("MERGE_HEAD").is_file() {
+        Some("merge")
+    } else if git_dir.join("CHERRY_PICK_HEAD").is_file() {
+        Some("cherry-pick")
+    } else {
+        None
+    };
+    Ok(state)
+}
+
+/// Path of the log file where `cargo husky run` appends timings of commands.
+pub fn timings_log_path(hooks_dir: &Path) -> PathBuf {
+    hooks_dir
+        .parent()
+        .unwrap_or(hooks_dir)
+        .join("cargo-husky-timings.log")
+}
+
+/// Run commands of the hook installed with `native-runner` feature and return the exit status of
+/// the hook. With fail-fast policy (default), it stops at the first failure. With run-all policy,
+/// all commands are run and failures are reported at the end. When `jobs` is greater than 1,
+/// commands are run in parallel. Result of each command and the summary are shown with colors when
+/// stdout is a terminal. With quiet output, output of each command is only shown when it fails.
+/// When `$CARGO_HUSKY_OUTPUT` is `json`, results of commands are reported in JSON. With
+/// `affected-only`, cargo commands only check workspace members affected by the changes. Commands
+/// not listed in `in-progress.commands` are skipped while rebase, merge or cherry-pick is in
+/// progress. Commands only for releases are skipped unless the hook script detected that tags or
+/// release branches are pushed.
+pub fn run_hook(hooks_dir: &Path, hook: &str) -> Result<i32> {
+    let mut plan = Plan::load(&plan_path(hooks_dir, hook))?;
+    if plan.affected_only {
+        plan.steps = affected_steps(hook, mem::take(&mut plan.steps));
+    }
+    if plan.steps.iter().any(|s| s.skip_in_progress) {
+        if let Some(state) = in_progress()? {
+            plan.steps.retain(|step| {
+                if step.skip_in_progress {
+                    eprintln!(
+                        "cargo-husky: Skipping '{}' during {}",
+                        step.display(),
+                        state
+                    );
+                }
+                !step.skip_in_progress
+            });
+        }
+    }
+    // Set by the hook script reading refs to be pushed
+    if !matches!(env::var_os("CARGO_HUSKY_RELEASE"), Some(v) if !v.is_empty()) {
+        plan.steps.retain(|step| {
+            if step.release_only {
+                eprintln!(
+                    "cargo-husky: Skipping '{}' since no tag or release branch is pushed",
+                    step.display()
+                );
+            }
+            !step.release_only
+        });
+    }
+    let output = env::var("CARGO_HUSKY_OUTPUT").unwrap_or_default();
+    let quiet = match output.as_str() {
+        "quiet" => true,
+        "full" => false,
+        _ => plan.quiet,
+    };
+    let mode = if output == "json" {
+        OutputMode::Json
+    } else if quiet {
+        OutputMode::Quiet
+    } else if plan.jobs > 1 {
+        OutputMode::Labeled
+    } else {
+        OutputMode::Full
+    };
+    let results = if plan.jobs <= 1 {
+        run_sequential(&plan.steps, plan.fail_fast, mode)
+    } else {
+        run_parallel(&plan.steps, plan.jobs, plan.fail_fast, mode)
+    };
+    if mode == OutputMode::Json {
+        println!("{}", results_json(hook, &plan.steps, &results));
+    }
+    if plan.timings {
+        print_timings(hook, &plan.steps, &results);
+    }
+    if plan.timings_log {
+        let path = timings_log_path(hooks_dir);
+        if let Err(err) = log_timings(&path, hook, &plan.steps, &results) {
+            eprintln!(
+                "cargo-husky: Could not write timings to {:?}: {}",
+                path, err
+            );
+        }
+    }
+
+    let passed = results.iter().flatten().all(StepResult::succeeded);
+    if passed && mode != OutputMode::Json && !plan.steps.is_empty() {
+        println!("{}", paint(32, "[cargo-husky] All commands passed"));
+    }
+
+    if plan.jobs <= 1 && plan.fail_fast {
+        // Exit with the status of the failed command as shell script does
+        for result in results.into_iter().flatten() {
+            match result.status {
+                Ok(0) => {}
+                status => return status,
+            }
+        }
+        return Ok(0);
+    }
+
+    let mut failures = vec![];
+    let mut skipped = 0;
+    for (step, result) in plan.steps.iter().zip(results) {
+        match result.map(|r| r.status) {
+            Some(Ok(0)) => {}
+            Some(Ok(code)) => failures.push(format!("{} (exit status {})", step.display(), code)),
+            Some(Err(err)) => failures.push(format!("{} ({})", step.display(), err)),
+            None => skipped += 1,
+        }
+    }
+    if failures.is_empty() {
+        return Ok(0);
+    }
+    eprintln!(
+        "cargo-husky: {} of {} commands failed:",
+        failures.len(),
+        plan.steps.len()
+    );
+    for failure in failures {
+        eprintln!("    {}", failure);
+    }
+    if skipped > 0 {
+        eprintln!(
+            "cargo-husky: {} commands were not run due to fail-fast policy",
+            skipped
+        );
+    }
+    Ok(1)
+}
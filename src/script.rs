@@ -0,0 +1,1960 @@
+use config::Config;
+use error::{Error, Result};
+use hook::{
+    generated_hooks, hook_reads_stdin, CONFIG_HASH_HEADER, MANAGED_BLOCK_BEGIN, MANAGED_BLOCK_END,
+};
+use plan::{Plan, Step};
+use std::{env, path};
+
+/// Quote the string as one word of shell script.
+pub fn quote(s: &str) -> String {
+    if !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-_./=:,@%".contains(c))
+    {
+        return s.to_string();
+    }
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Escape special characters in double-quoted string of shell script.
+pub fn escape_double_quoted(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        if "\\\"$`".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Split the command line into words like shell. Quotes and backslashes are removed. Shell features
+// such as pipes, redirects and variables are not supported. `None` means a quote is not closed.
+fn split_words(line: &str) -> Option<Vec<String>> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => w.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c if "\\\"$`".contains(c) => w.push(c),
+                            c => {
+                                w.push('\\');
+                                w.push(c);
+                            }
+                        },
+                        c => w.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    word.get_or_insert_with(String::new).push(c);
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+/// Shebang of hook scripts generated without configuration. `/bin/sh` does not exist on some
+/// systems such as NixOS so `sh` is looked up in `$PATH`.
+pub const DEFAULT_SHEBANG: &str = "#!/usr/bin/env sh";
+
+// Shebang configured by `shebang`. A path such as `/bin/bash` is used as-is and a command name such
+// as `bash` is looked up in `$PATH` by `env`.
+pub fn shebang(config: &Config) -> Result<String> {
+    match config.get_str("shebang")?.map(str::trim) {
+        None => Ok(DEFAULT_SHEBANG.to_string()),
+        Some("") => Err(Error::InvalidConfig(
+            "shebang".to_string(),
+            "interpreter",
+            "empty string",
+        )),
+        Some(interp) if interp.contains('/') => Ok(format!("#!{}", interp)),
+        Some(interp) => Ok(format!("#!/usr/bin/env {}", interp)),
+    }
+}
+
+// Options set at the beginning of hook scripts. Scripts stop at a failed command or an unset
+// variable. pipefail is not available in POSIX sh so it is only set for shells which support it.
+fn shell_options(shebang: &str) -> &'static str {
+    let interp = shebang
+        .trim_start_matches("#!")
+        .split_whitespace()
+        .map(|w| w.rsplit('/').next().unwrap_or(w))
+        .find(|w| *w != "env" && !w.starts_with('-'));
+    match interp {
+        Some("bash") | Some("zsh") | Some("ksh") => "set -eu\nset -o pipefail",
+        _ => "set -eu",
+    }
+}
+
+// Make a pattern of `case` statement. Glob characters are kept as-is and other special characters
+// are escaped.
+fn case_pattern(glob: &str) -> String {
+    let mut pat = String::new();
+    for c in glob.chars() {
+        if !c.is_ascii_alphanumeric() && !"*?[]-_./!".contains(c) {
+            pat.push('\\');
+        }
+        pat.push(c);
+    }
+    pat
+}
+
+// Function to print a line with the SGR color code. Colors are disabled when stdout is not a
+// terminal or `$NO_COLOR` is set.
+const COLOR_FUNC: &str = concat!(
+    "\ncargo_husky_color() {\n",
+    "    if [ -t 1 ] && [ -z \"${NO_COLOR:-}\" ]; then\n",
+    "        printf '\\033[%sm%s\\033[0m\\n' \"$1\" \"$2\"\n",
+    "    else\n",
+    "        printf '%s\\n' \"$2\"\n",
+    "    fi\n",
+    "}\n",
+);
+
+// Header printed before running the command
+fn header(display: &str) -> String {
+    format!(
+        "cargo_husky_color 1 {}",
+        quote(&format!("[cargo-husky] Running {}", display))
+    )
+}
+
+// Show the command line and run the command
+fn run_line(display: &str, exec: &str) -> String {
+    format!("\n{}\n{}", header(display), exec)
+}
+
+fn raw_cmd(c: &str) -> String {
+    run_line(c, c)
+}
+
+// Check a cargo subcommand which is not bundled with cargo is installed. When it is not installed,
+// the hook fails with an instruction to install it.
+fn require_cmd(sub: &str, krate: &str) -> String {
+    require_program(&format!("cargo {}", sub), krate)
+}
+
+fn require_program(prog: &str, krate: &str) -> String {
+    format!(
+        "\nif ! {} --version > /dev/null 2>&1; then\n    echo \"cargo-husky: '{}' is not installed. Please install it by 'cargo install {}'\" >&2\n    exit 1\nfi",
+        prog, prog, krate,
+    )
+}
+
+// Skip the command with a warning when the program is not installed
+fn skip_unless_installed(prog: &str, krate: &str) -> String {
+    format!(
+        "\nif ! {} --version > /dev/null 2>&1; then\n    echo \"cargo-husky: '{}' is not installed. Skipping it. Install it by 'cargo install {}'\" >&2\n    exit 0\nfi",
+        prog, prog, krate,
+    )
+}
+
+fn external_cmd(sub: &str, krate: &str, c: &str) -> String {
+    require_cmd(sub, krate) + &raw_cmd(c)
+}
+
+// Whether the command is run for all crates in the workspace. `commands.<name>.all` overrides
+// `run-for-all` feature.
+fn for_all(name: &str, config: &Config) -> Result<bool> {
+    let key = format!("commands.{}.all", name);
+    Ok(config
+        .get_bool(&key)?
+        .unwrap_or(cfg!(feature = "run-for-all")))
+}
+
+// Some commands such as cargo-nextest do not accept --all
+fn workspace_step(args: &[&str], all: bool) -> Step {
+    let mut step = Step::new(args);
+    step.per_package = true;
+    if all {
+        step.args.push("--workspace".to_string());
+    }
+    step
+}
+
+fn cargo_step(args: &[&str], subflags: &[&str], all: bool) -> Step {
+    let mut step = Step::new(args);
+    step.per_package = true;
+    if all {
+        step.args.push("--all".to_string());
+    }
+    if !subflags.is_empty() {
+        step.args.push("--".to_string());
+        step.args.extend(subflags.iter().map(|f| f.to_string()));
+    }
+    step
+}
+
+// `--locked`, `--offline` or `--frozen` options enabled by features or package metadata
+fn lock_flags(config: &Config) -> Result<Vec<&'static str>> {
+    let enabled = |name: &str, feature: bool| -> Result<bool> {
+        Ok(config.get_bool(name)?.unwrap_or(feature))
+    };
+    if enabled("frozen", cfg!(feature = "frozen"))? {
+        return Ok(vec!["--frozen"]);
+    }
+    let mut flags = vec![];
+    if enabled("locked", cfg!(feature = "locked"))? {
+        flags.push("--locked");
+    }
+    if enabled("offline", cfg!(feature = "offline"))? {
+        flags.push("--offline");
+    }
+    Ok(flags)
+}
+
+// Insert cargo options before `--` separator
+fn add_cargo_flags(args: &mut Vec<String>, flags: &[&str]) {
+    let pos = args.iter().position(|a| a == "--").unwrap_or(args.len());
+    for (i, flag) in flags.iter().enumerate() {
+        args.insert(pos + i, flag.to_string());
+    }
+}
+
+fn external_step(mut step: Step, krate: &str) -> Step {
+    step.requires = Some(krate.to_string());
+    step
+}
+
+fn semver_checks_step(config: &Config) -> Result<Step> {
+    let mut step = workspace_step(
+        &["cargo", "semver-checks", "check-release"],
+        for_all("semver-checks", config)?,
+    );
+    if let Some(rev) = config.get_str("semver-checks.baseline-rev")? {
+        step.args.push("--baseline-rev".to_string());
+        step.args.push(rev.to_string());
+    } else if let Some(ver) = config.get_str("semver-checks.baseline-version")? {
+        step.args.push("--baseline-version".to_string());
+        step.args.push(ver.to_string());
+    }
+    Ok(external_step(step, "cargo-semver-checks"))
+}
+
+// Check the crate with the minimum supported Rust version. `msrv.toolchain` overrides
+// `rust-version` in Cargo.toml.
+fn msrv_step(config: &Config) -> Result<Step> {
+    let toolchain = match config.get_str("msrv.toolchain")? {
+        Some(toolchain) => toolchain,
+        None => config.rust_version().ok_or(Error::RustVersionNotFound)?,
+    };
+    Ok(cargo_step(
+        &["cargo", &format!("+{}", toolchain), "check"],
+        &[],
+        for_all("msrv", config)?,
+    ))
+}
+
+// Check each combination of features. Only the configured packages are checked when
+// `hack.packages` is set.
+fn hack_step(config: &Config) -> Result<Step> {
+    let key = "hack.depth";
+    let depth = match config.get_int(key)? {
+        Some(depth) if depth < 1 => {
+            return Err(Error::InvalidConfig(
+                key.to_string(),
+                "positive integer",
+                "non-positive integer",
+            ))
+        }
+        Some(depth) => depth,
+        None => 2,
+    };
+    let packages = config.get_str_array("hack.packages")?.unwrap_or_default();
+    let all = packages.is_empty() && for_all("hack", config)?;
+    let mut step = workspace_step(&["cargo", "hack", "check", "--feature-powerset"], all);
+    step.args.push("--depth".to_string());
+    step.args.push(depth.to_string());
+    for package in &packages {
+        step.args.push("--package".to_string());
+        step.args.push(package.to_string());
+    }
+    step.per_package = packages.is_empty();
+    Ok(external_step(step, "cargo-hack"))
+}
+
+// Delegate to a task runner. The task is configured per hook by `<runner>.<hook>` such as
+// `just.pre-push`.
+fn task_step(name: &str, hook: &str, config: &Config) -> Result<Step> {
+    let default = match name {
+        "just" => hook.replace('-', ""),
+        "make" => "check".to_string(),
+        _ => "ci-flow".to_string(),
+    };
+    let task = config
+        .get_str(&format!("{}.{}", name, hook))?
+        .map(str::to_string)
+        .unwrap_or(default);
+    Ok(match name {
+        "just" => external_step(Step::new(&["just", &task]), "just"),
+        "make" => Step::new(&["make", &task]),
+        _ => external_step(Step::new(&["cargo", "make", &task]), "cargo-make"),
+    })
+}
+
+// Miri requires nightly toolchain. Running all tests with Miri is very slow so they can be filtered.
+fn miri_step(config: &Config) -> Result<Step> {
+    let toolchain = config.get_str("miri.toolchain")?.unwrap_or("nightly");
+    let filter = config
+        .get_str("miri.filter")?
+        .into_iter()
+        .collect::<Vec<_>>();
+    Ok(cargo_step(
+        &["cargo", &format!("+{}", toolchain), "miri", "test"],
+        &filter,
+        for_all("miri", config)?,
+    ))
+}
+
+fn udeps_step(config: &Config) -> Result<Step> {
+    // cargo-udeps requires nightly toolchain. When the default toolchain is not nightly, it can be
+    // specified in metadata.
+    let all = for_all("udeps", config)?;
+    let step = match config.get_str("udeps.toolchain")? {
+        Some(toolchain) => workspace_step(&["cargo", &format!("+{}", toolchain), "udeps"], all),
+        None => workspace_step(&["cargo", "udeps"], all),
+    };
+    Ok(external_step(step, "cargo-udeps"))
+}
+
+// Staged files matching the pathspec separated by NUL. Deleted files are excluded.
+// Paths are relative to the current directory and files outside of it are excluded (`working-dir`).
+fn staged_files(pathspec: &[&str]) -> String {
+    let pathspec = pathspec
+        .iter()
+        .map(|p| quote(p))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "git diff --cached --name-only --relative --diff-filter=ACMR -z -- {}",
+        pathspec
+    )
+}
+
+// Manifests which `cargo sort` checks with `staged-only` feature
+const MANIFEST_PATHSPEC: &[&str] = &["Cargo.toml", "*/Cargo.toml"];
+
+// Find typos by typos-cli. Only staged files are checked in `pre-commit` hook. The hook does not
+// fail when it is not installed.
+fn typos_step(hook: &str) -> Step {
+    let mut step = external_step(Step::new(&["typos"]), "typos-cli");
+    step.optional = true;
+    if hook == "pre-commit" {
+        // Excluded files in the configuration of typos are not checked even if they are staged
+        step.args.push("--force-exclude".to_string());
+        step.staged = true;
+        step.pathspec = vec!["*".to_string()];
+    }
+    step
+}
+
+// Check dependency tables in manifests are sorted. Only staged manifests are checked with
+// `staged-only` feature.
+fn sort_step(staged_only: bool, all: bool) -> Step {
+    let mut step = Step::new(&["cargo", "sort", "--check"]);
+    if staged_only {
+        step.staged = true;
+        step.pathspec = MANIFEST_PATHSPEC.iter().map(|p| p.to_string()).collect();
+    } else if all {
+        step.args.push("--workspace".to_string());
+    }
+    external_step(step, "cargo-sort")
+}
+
+// Check or fix formatting of only staged files by running rustfmt directly
+fn rustfmt_step(config: &Config, check: bool) -> Step {
+    let mut step = Step::new(&["rustfmt"]);
+    if check {
+        step.args.push("--check".to_string());
+    }
+    if let Some(edition) = config.edition() {
+        step.args.push("--edition".to_string());
+        step.args.push(edition.to_string());
+    }
+    step.staged = true;
+    step
+}
+
+// Command line to run the step. When timeout is configured, the command is run with `timeout`
+// command if it is available.
+fn exec_line(step: &Step) -> String {
+    match step.timeout {
+        Some(secs) => {
+            let mut words = step
+                .env
+                .iter()
+                .map(|(k, v)| format!("{}=\"{}\"", k, escape_double_quoted(v)))
+                .collect::<Vec<_>>();
+            words.push(format!(
+                "$(command -v timeout > /dev/null 2>&1 && echo timeout {})",
+                secs
+            ));
+            words.extend(step.args.iter().map(|a| quote(a)));
+            words.join(" ")
+        }
+        None => step.display(),
+    }
+}
+
+// Command substitution in the command line of the step with timeout is split into words
+// intentionally. ShellCheck directive to allow it is put before the line.
+fn timeout_directive(step: &Step, indent: &str) -> String {
+    if step.timeout.is_some() {
+        format!("{}# shellcheck disable=SC2046\n", indent)
+    } else {
+        String::new()
+    }
+}
+
+fn step_script(step: &Step, quiet: bool) -> String {
+    let c = step.display();
+    let exec = exec_line(step);
+    let sub = step.subcommand()[1..]
+        .iter()
+        .map(|a| quote(a))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let require = |krate: &str| {
+        let prog = step
+            .subcommand()
+            .iter()
+            .map(|a| quote(a))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if step.optional {
+            skip_unless_installed(&prog, krate)
+        } else {
+            require_program(&prog, krate)
+        }
+    };
+    let script = if step.staged {
+        let files = staged_files(&step.staged_pathspec());
+        // Without any file, the command would check all files or read stdin
+        format!(
+            "{}\nif [ -z \"$({} | tr -d '\\0')\" ]; then\n    exit 0\nfi\n{}\n{}{} | xargs -0 {}",
+            step.requires.as_deref().map(require).unwrap_or_default(),
+            files,
+            header(&format!("{} <staged files>", c)),
+            timeout_directive(step, ""),
+            files,
+            exec
+        )
+    } else {
+        let directed = timeout_directive(step, "") + &exec;
+        match (&step.requires, &step.fallback) {
+            (Some(_), Some(fallback)) => {
+                let fallback = Step {
+                    timeout: step.timeout,
+                    ..Step::new(fallback)
+                };
+                format!(
+                    "\nif cargo {} --version > /dev/null 2>&1; then\n    {}\n{}    {}\nelse\n    echo \"cargo-husky: 'cargo {}' is not installed. Falling back to '{}'\" >&2{}\nfi",
+                    sub,
+                    header(&c),
+                    timeout_directive(step, "    "),
+                    exec,
+                    sub,
+                    fallback.subcommand().join(" "),
+                    run_line(
+                        &fallback.display(),
+                        &(timeout_directive(&fallback, "") + &exec_line(&fallback))
+                    ),
+                )
+            }
+            (Some(krate), None) => require(krate) + &run_line(&c, &directed),
+            (None, _) => run_line(&c, &directed),
+        }
+    };
+    // Status of the command is shown after running it. With quiet output, output of the command is
+    // captured and only shown when the command fails.
+    let (run, show_output) = if quiet {
+        (
+            format!("cargo_husky_output=\"$( ({}\n) 2>&1)\"", script),
+            "    printf '%s\\n' \"$cargo_husky_output\"\n",
+        )
+    } else {
+        (format!("({}\n)", script), "")
+    };
+    let script = format!(
+        concat!(
+            "\nif {}; then\n",
+            "    cargo_husky_color 32 {}\n",
+            "else\n",
+            "    cargo_husky_status=$?\n",
+            "{}",
+            "    cargo_husky_color 31 \"{} (exit status $cargo_husky_status)\"\n",
+            "    exit \"$cargo_husky_status\"\n",
+            "fi",
+        ),
+        run,
+        quote(&format!("PASS: {}", c)),
+        show_output,
+        escape_double_quoted(&format!("FAIL: {}", c)),
+    );
+    let script = if step.allow_failure {
+        format!(
+            "\nif ! ({}\n); then\n    echo \"cargo-husky: '{}' failed but it is allowed to fail\" >&2\nfi",
+            script,
+            escape_double_quoted(&c)
+        )
+    } else {
+        script
+    };
+    let script = if step.release_only {
+        format!(
+            "\nif [ -n \"${{CARGO_HUSKY_RELEASE:-}}\" ]; then{}\nfi",
+            script
+        )
+    } else {
+        script
+    };
+    if step.skip_in_progress {
+        format!(
+            "\nif [ -z \"$cargo_husky_in_progress\" ]; then{}\nfi",
+            script
+        )
+    } else {
+        script
+    }
+}
+
+// Format files and stage the formatted files again. Files which have unstaged changes cannot be
+// staged safely since their unstaged changes would be committed.
+fn autofix_cmd(config: &Config, staged_only: bool) -> Result<String> {
+    let fmt = if staged_only {
+        rustfmt_step(config, false)
+    } else {
+        cargo_step(&["cargo", "fmt"], &[], for_all("fmt", config)?)
+    };
+    Ok(format!(
+        concat!(
+            "\nunstaged_rust_files=\"$(git -c core.quotePath=false diff --name-only --relative -- '*.rs')\"",
+            "{}\n",
+            "git -c core.quotePath=false diff --cached --name-only --relative --diff-filter=ACMR -- '*.rs' | while IFS= read -r file; do\n",
+            "    if git diff --quiet -- \"$file\"; then\n",
+            "        continue\n",
+            "    fi\n",
+            "    if printf '%s\\n' \"$unstaged_rust_files\" | grep -qxF -- \"$file\"; then\n",
+            "        echo \"cargo-husky: '$file' was formatted but it has unstaged changes. Please stage it and commit again\" >&2\n",
+            "        exit 1\n",
+            "    fi\n",
+            "    echo \"cargo-husky: Staging formatted file '$file'\"\n",
+            "    git add -- \"$file\"\n",
+            "done",
+        ),
+        step_script(&fmt, false)
+    ))
+}
+
+// Change the current directory to `working-dir` which is relative to the root of the repository.
+// It is for the repository where the cargo project is put in its subdirectory.
+fn change_working_dir(config: &Config) -> Result<String> {
+    let dir = match config.get_str("working-dir")? {
+        Some(dir) => dir,
+        None => return Ok(String::new()),
+    };
+    if path::Path::new(dir).is_absolute() {
+        return Err(Error::InvalidConfig(
+            "working-dir".to_string(),
+            "relative path",
+            "absolute path",
+        ));
+    }
+    Ok(format!(
+        "\ncd {} || {{\n    echo \"cargo-husky: Directory '{}' in working-dir does not exist\" >&2\n    exit 1\n}}\n",
+        quote(dir),
+        escape_double_quoted(dir)
+    ))
+}
+
+// Export variables in the env file such as `.env` if it exists. The file is parsed line by line and
+// is never sourced so that it cannot run arbitrary code. Empty lines, comment lines and `export`
+// prefix are ignored. Quotes around values are removed.
+fn load_env_file(path: &str) -> String {
+    format!(
+        concat!(
+            "\nif [ -f {0} ]; then\n",
+            "    cargo_husky_cr=\"$(printf '\\r')\"\n",
+            "    while IFS= read -r cargo_husky_line || [ -n \"$cargo_husky_line\" ]; do\n",
+            "        cargo_husky_line=\"${{cargo_husky_line%\"$cargo_husky_cr\"}}\"\n",
+            "        cargo_husky_line=\"${{cargo_husky_line#\"${{cargo_husky_line%%[![:space:]]*}}\"}}\"\n",
+            "        case \"$cargo_husky_line\" in\n",
+            "            ''|'#'*) continue ;;\n",
+            "            'export '*) cargo_husky_line=\"${{cargo_husky_line#export }}\" ;;\n",
+            "        esac\n",
+            "        cargo_husky_name=\"${{cargo_husky_line%%=*}}\"\n",
+            "        cargo_husky_value=\"${{cargo_husky_line#*=}}\"\n",
+            "        case \"$cargo_husky_name\" in\n",
+            "            \"$cargo_husky_line\"|''|[0-9]*|*[!A-Za-z0-9_]*)\n",
+            "                echo \"cargo-husky: Ignored invalid line in {1}: $cargo_husky_line\" >&2\n",
+            "                continue\n",
+            "                ;;\n",
+            "        esac\n",
+            "        case \"$cargo_husky_value\" in\n",
+            "            \\\"*\\\") cargo_husky_value=\"${{cargo_husky_value#?}}\"; cargo_husky_value=\"${{cargo_husky_value%?}}\" ;;\n",
+            "            \\'*\\') cargo_husky_value=\"${{cargo_husky_value#?}}\"; cargo_husky_value=\"${{cargo_husky_value%?}}\" ;;\n",
+            "        esac\n",
+            "        export \"$cargo_husky_name=$cargo_husky_value\"\n",
+            "    done < {0}\n",
+            "fi\n",
+        ),
+        quote(path),
+        escape_double_quoted(path)
+    )
+}
+
+// Export environment variables configured in `env` table so that commands are run with the same
+// settings as CI. They are inherited by `cargo husky run` as well.
+fn exports(config: &Config) -> Result<String> {
+    let mut s = String::new();
+    if let Some(dir) = config.get_str("target-dir")? {
+        // Builds of hooks don't invalidate incremental build cache of the regular target directory.
+        s += &format!("\nexport CARGO_TARGET_DIR={}\n", quote(dir));
+    }
+    if let Some(path) = config.get_str("env-file")? {
+        s += &load_env_file(path);
+    }
+    let vars = config.get_str_table("env")?.unwrap_or_default();
+    if !vars.is_empty() {
+        s.push('\n');
+    }
+    for (name, value) in vars {
+        let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(Error::InvalidConfig(
+                format!("env.{}", name),
+                "environment variable name",
+                "invalid name",
+            ));
+        }
+        s += &format!("export {}={}\n", name, quote(value));
+    }
+    Ok(s)
+}
+
+// Add the directory of cargo to `$PATH` when cargo is not found. Git clients launched from GUI
+// such as VS Code do not inherit `$PATH` configured in shell profile. Cargo installed by rustup
+// selects the toolchain with rust-toolchain.toml in the current directory.
+fn locate_cargo() -> &'static str {
+    concat!(
+        "\nif ! command -v cargo > /dev/null 2>&1; then\n",
+        "    cargo_husky_cargo_bin=\"${CARGO_HOME:-${HOME:-}/.cargo}/bin\"\n",
+        "    if [ -x \"$cargo_husky_cargo_bin/cargo\" ]; then\n",
+        "        PATH=\"$cargo_husky_cargo_bin${PATH:+:$PATH}\"\n",
+        "    elif cargo_husky_cargo=\"$(rustup which cargo 2> /dev/null)\"; then\n",
+        "        PATH=\"$(dirname \"$cargo_husky_cargo\")${PATH:+:$PATH}\"\n",
+        "    else\n",
+        "        echo \"cargo-husky: 'cargo' is not found in \\$PATH. Add the directory of cargo to \\$PATH of your Git client or install Rust via rustup (https://rustup.rs)\" >&2\n",
+        "        exit 1\n",
+        "    fi\n",
+        "    export PATH\n",
+        "fi\n",
+    )
+}
+
+// Validate the current branch name with the extended regular expression. Detached HEAD is not
+// checked.
+fn validate_branch_name(pattern: &str, message: Option<&str>) -> String {
+    let message = match message {
+        Some(msg) => quote(&format!("cargo-husky: {}", msg)),
+        None => format!(
+            "\"cargo-husky: Branch name '$branch' does not match pattern '{}'\"",
+            escape_double_quoted(pattern)
+        ),
+    };
+    format!(
+        concat!(
+            "\nbranch=\"$(git symbolic-ref --quiet --short HEAD || true)\"\n",
+            "if [ -n \"$branch\" ] && ! printf '%s\\n' \"$branch\" | grep -Eq {}; then\n",
+            "    echo {} >&2\n",
+            "    exit 1\n",
+            "fi\n",
+        ),
+        quote(&format!("^({})$", pattern)),
+        message
+    )
+}
+
+// Prepend the issue ID extracted from the current branch name to the commit message. Messages of
+// merge and squash commits and messages reused by `--amend` or `-C` are kept as they are. Nothing
+// is done when the message already contains the ID.
+fn inject_issue_id(pattern: &str) -> String {
+    format!(
+        concat!(
+            "\ncase \"${{2:-}}\" in\n",
+            "    merge|squash|commit) ;;\n",
+            "    *)\n",
+            "        issue_id=\"$( (git symbolic-ref --quiet --short HEAD || true) | grep -Eo {} | head -n 1 || true)\"\n",
+            "        if [ -n \"$issue_id\" ] && ! grep -v '^#' \"$1\" | grep -qF -- \"$issue_id\"; then\n",
+            "            cargo_husky_msg=\"$(mktemp)\"\n",
+            "            {{ printf '%s: ' \"$issue_id\"; cat \"$1\"; }} > \"$cargo_husky_msg\"\n",
+            "            cat \"$cargo_husky_msg\" > \"$1\"\n",
+            "            rm -f \"$cargo_husky_msg\"\n",
+            "        fi\n",
+            "        ;;\n",
+            "esac\n",
+        ),
+        quote(pattern)
+    )
+}
+
+// Explain the hooks and ask the developer to confirm running them on the first run (ask-consent
+// feature) since build scripts cannot prompt. The answer is saved in `.git/cargo-husky-consent`
+// shared by all hooks and worktrees. Without a terminal (e.g. CI or GUI clients), hooks are run
+// without asking.
+fn ask_consent(config: &Config) -> Result<String> {
+    let mut s = format!(
+        concat!(
+            "\ncargo_husky_consent=\"$(git rev-parse --git-common-dir)/cargo-husky-consent\"\n",
+            "if [ ! -f \"$cargo_husky_consent\" ] && (: < /dev/tty) 2>/dev/null; then\n",
+            "    {{\n",
+            "        echo {}\n",
+        ),
+        quote(&format!(
+            "cargo-husky: Git hooks of this repository were installed by cargo-husky ({})",
+            env!("CARGO_PKG_HOMEPAGE")
+        ))
+    );
+    for hook in generated_hooks() {
+        let commands = steps(hook, config)?
+            .iter()
+            .map(Step::display)
+            .collect::<Vec<_>>();
+        let line = if commands.is_empty() {
+            format!("  {}", hook)
+        } else {
+            format!("  {}: {}", hook, commands.join(", "))
+        };
+        s += &format!("        echo {}\n", quote(&line));
+    }
+    s += concat!(
+        "        printf 'Run these hooks? [Y/n] '\n",
+        "    } >&2\n",
+        "    read -r cargo_husky_answer < /dev/tty || cargo_husky_answer=n\n",
+        "    case \"$cargo_husky_answer\" in\n",
+        "        '' | [Yy] | [Yy][Ee][Ss]) echo yes > \"$cargo_husky_consent\" ;;\n",
+        "        *) echo no > \"$cargo_husky_consent\" ;;\n",
+        "    esac\n",
+        "fi\n",
+        "if [ \"$(cat \"$cargo_husky_consent\" 2>/dev/null)\" = no ]; then\n",
+        "    echo \"cargo-husky: Skipped $(basename \"$0\") hook since running hooks was declined. Remove $cargo_husky_consent to be asked again\" >&2\n",
+        "    exit 0\n",
+        "fi\n",
+    );
+    Ok(s)
+}
+
+// Show reminders such as updating the changelog after each commit
+fn show_reminders(reminders: &[&str]) -> String {
+    let mut s = "\n".to_string();
+    for reminder in reminders {
+        s += &format!(
+            "cargo_husky_color 33 {}\n",
+            quote(&format!("[cargo-husky] Reminder: {}", reminder))
+        );
+    }
+    s
+}
+
+// Reminders shown by `post-commit` hook
+fn reminders<'a>(hook: &str, config: &'a Config) -> Result<Vec<&'a str>> {
+    if hook != "post-commit" {
+        return Ok(vec![]);
+    }
+    Ok(config
+        .get_str_array("post-commit.reminders")?
+        .unwrap_or_default())
+}
+
+// Detect tags or release branches are pushed. Commands only for releases are run when
+// `$CARGO_HUSKY_RELEASE` is set. It is exported for `cargo husky run`.
+fn detect_release_push(branches: &[&str]) -> String {
+    let pats = std::iter::once("refs/tags/*".to_string())
+        .chain(
+            branches
+                .iter()
+                .map(|b| format!("refs/heads/{}", case_pattern(b))),
+        )
+        .collect::<Vec<_>>()
+        .join("|");
+    format!(
+        concat!(
+            "\nCARGO_HUSKY_RELEASE=\n",
+            "while read -r _ _ remote_ref _; do\n",
+            "    case \"$remote_ref\" in\n",
+            "        {})\n",
+            "            CARGO_HUSKY_RELEASE=1\n",
+            "            ;;\n",
+            "    esac\n",
+            "done < \"$hook_stdin\"\n",
+            "export CARGO_HUSKY_RELEASE\n",
+        ),
+        pats
+    )
+}
+
+// Refuse pushing to protected branches. Glob patterns such as `release/*` are available.
+fn refuse_protected_push(branches: &[&str]) -> String {
+    let pats = branches
+        .iter()
+        .map(|b| format!("refs/heads/{}", case_pattern(b)))
+        .collect::<Vec<_>>()
+        .join("|");
+    format!(
+        concat!(
+            "\nwhile read -r _ _ remote_ref _; do\n",
+            "    case \"$remote_ref\" in\n",
+            "        {})\n",
+            "            echo \"cargo-husky: Pushing to protected branch '${{remote_ref#refs/heads/}}' is not allowed\" >&2\n",
+            "            exit 1\n",
+            "            ;;\n",
+            "    esac\n",
+            "done < \"$hook_stdin\"\n",
+        ),
+        pats
+    )
+}
+
+// Refuse committing to protected branches directly
+fn refuse_protected_commit(branches: &[&str]) -> String {
+    let pats = branches
+        .iter()
+        .map(|b| case_pattern(b))
+        .collect::<Vec<_>>()
+        .join("|");
+    format!(
+        concat!(
+            "\ncase \"$(git symbolic-ref --quiet --short HEAD || true)\" in\n",
+            "    {})\n",
+            "        echo \"cargo-husky: Committing to protected branch '$(git symbolic-ref --short HEAD)' directly is not allowed\" >&2\n",
+            "        echo \"cargo-husky: Please create a new branch by 'git switch -c <branch>' and commit to it\" >&2\n",
+            "        exit 1\n",
+            "        ;;\n",
+            "esac\n",
+        ),
+        pats
+    )
+}
+
+// Refuse rebasing protected branches. The branch being rebased is given as the second argument, or
+// it is the current branch when omitted.
+fn refuse_protected_rebase(branches: &[&str]) -> String {
+    let pats = branches
+        .iter()
+        .map(|b| case_pattern(b))
+        .collect::<Vec<_>>()
+        .join("|");
+    format!(
+        concat!(
+            "\nbranch=\"${{2:-$(git symbolic-ref --quiet --short HEAD || true)}}\"\n",
+            "case \"${{branch#refs/heads/}}\" in\n",
+            "    {})\n",
+            "        echo \"cargo-husky: Rebasing protected branch '${{branch#refs/heads/}}' is not allowed\" >&2\n",
+            "        exit 1\n",
+            "        ;;\n",
+            "esac\n",
+        ),
+        pats
+    )
+}
+
+// Fetch dependencies when `Cargo.lock` was changed by switching branches or rewriting commits, and
+// remind to install the toolchain when the toolchain file was changed. Checking out files does not
+// switch branches.
+fn refresh_caches(hook: &str) -> String {
+    let (prelude, cond, revs) = match hook {
+        "post-checkout" => ("", "[ \"${3:-}\" = 1 ] && ", "\"$1\" \"$2\""),
+        _ => (
+            concat!(
+                "\ncase \"${1:-}\" in\n",
+                "    amend) cargo_husky_old='HEAD@{1}' ;;\n",
+                "    *) cargo_husky_old=ORIG_HEAD ;;\n",
+                "esac\n",
+            ),
+            "",
+            "\"$cargo_husky_old\" HEAD",
+        ),
+    };
+    format!(
+        concat!(
+            "{}",
+            "\nif {}! git diff --quiet {} -- Cargo.lock 2>/dev/null; then\n",
+            "    cargo_husky_color 33 '[cargo-husky] Cargo.lock was changed. Fetching dependencies'\n",
+            "    cargo fetch || echo 'cargo-husky: Failed to fetch dependencies' >&2\n",
+            "fi\n",
+            "if {}! git diff --quiet {} -- rust-toolchain.toml rust-toolchain 2>/dev/null; then\n",
+            "    cargo_husky_color 33 \"[cargo-husky] Rust toolchain was changed. Run 'rustup toolchain install' to install it\"\n",
+            "fi\n",
+        ),
+        prelude, cond, revs, cond, revs
+    )
+}
+
+// Scan lines added by staged changes and fail when they contain any of the patterns. Line numbers
+// are calculated from hunk headers of unified diff.
+fn check_markers(patterns: &[&str]) -> String {
+    let pats = patterns
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            format!(
+                "    pats[{}] = \"{}\"\n",
+                i,
+                p.replace('\\', "\\\\").replace('"', "\\\"")
+            )
+        })
+        .collect::<String>();
+    let program = format!(
+        concat!(
+            "\nBEGIN {{\n",
+            "{}",
+            "}}\n",
+            "/^diff --git / {{ header = 1; next }}\n",
+            "header && /^\\+\\+\\+ / {{ file = substr($0, 7); next }}\n",
+            "/^@@ / {{ header = 0; split($3, a, \",\"); line = substr(a[1], 2) + 0; next }}\n",
+            "!header && /^\\+/ {{\n",
+            "    for (i in pats) {{\n",
+            "        if (index(substr($0, 2), pats[i])) {{\n",
+            "            printf \"%s:%d: %s\\n\", file, line, substr($0, 2)\n",
+            "            found = 1\n",
+            "            break\n",
+            "        }}\n",
+            "    }}\n",
+            "    line++\n",
+            "}}\n",
+            "END {{ exit found }}\n",
+        ),
+        pats
+    );
+    format!(
+        concat!(
+            "\nif ! git -c core.quotePath=false diff --cached --no-color --no-ext-diff -U0 --diff-filter=ACM | awk {} >&2; then\n",
+            "    echo 'cargo-husky: Conflict markers or debug artifacts were found in staged changes' >&2\n",
+            "    exit 1\n",
+            "fi\n",
+        ),
+        quote(&program)
+    )
+}
+
+// Reject staged files larger than `max_size` bytes. Files tracked by git-lfs and files matching
+// the allowlist are not checked.
+fn check_large_files(max_size: i64, allow: &[&str]) -> String {
+    let allowed = if allow.is_empty() {
+        String::new()
+    } else {
+        let pats = allow
+            .iter()
+            .map(|p| case_pattern(p))
+            .collect::<Vec<_>>()
+            .join("|");
+        format!(
+            "    case \"$file\" in\n        {}) continue ;;\n    esac\n",
+            pats
+        )
+    };
+    format!(
+        concat!(
+            "\nlarge_files=\n",
+            "while IFS= read -r file; do\n",
+            "    if [ -z \"$file\" ]; then\n",
+            "        continue\n",
+            "    fi\n",
+            "{}",
+            "    case \"$(git check-attr --cached filter -- \"$file\")\" in\n",
+            "        *': filter: lfs') continue ;;\n",
+            "    esac\n",
+            "    size=\"$(git cat-file -s \":$file\")\"\n",
+            "    if [ \"$size\" -gt {} ]; then\n",
+            "        echo \"$file: $size bytes\" >&2\n",
+            "        large_files=true\n",
+            "    fi\n",
+            "done <<EOF\n",
+            "$(git -c core.quotePath=false diff --cached --name-only --diff-filter=ACM)\n",
+            "EOF\n",
+            "if [ -n \"$large_files\" ]; then\n",
+            "    echo 'cargo-husky: Files larger than {} bytes were staged. Track them with git-lfs or add them to large-files.allow' >&2\n",
+            "    exit 1\n",
+            "fi\n",
+        ),
+        allowed, max_size, max_size
+    )
+}
+
+// Files which affect results of cargo commands
+const RUST_PATHSPEC: &str = "'*.rs' Cargo.toml Cargo.lock '*/Cargo.toml' '*/Cargo.lock'";
+
+// Skip checks when no Rust source is changed in the pushed commits. Commits which are not known
+// locally are considered to be changed.
+fn skip_unchanged_push() -> String {
+    format!(
+        concat!(
+            "\nrust_changed=\n",
+            "while read -r _ local_sha _ remote_sha; do\n",
+            "    case \"$local_sha\" in\n",
+            "        *[!0]*) ;;\n",
+            "        *) continue ;; # Deleting the remote ref\n",
+            "    esac\n",
+            "    case \"$remote_sha\" in\n",
+            "        *[!0]*)\n",
+            "            if git cat-file -e \"$remote_sha^{{commit}}\" 2> /dev/null; then\n",
+            "                changed=\"$(git log --format= --name-only \"$local_sha\" --not \"$remote_sha\" -- {0})\"\n",
+            "            else\n",
+            "                changed=unknown\n",
+            "            fi\n",
+            "            ;;\n",
+            "        *) changed=\"$(git log --format= --name-only \"$local_sha\" --not --remotes -- {0})\" ;; # New remote ref\n",
+            "    esac\n",
+            "    if [ -n \"$changed\" ]; then\n",
+            "        rust_changed=true\n",
+            "    fi\n",
+            "done < \"$hook_stdin\"\n",
+            "if [ -z \"$rust_changed\" ]; then\n",
+            "    echo 'cargo-husky: No Rust source is changed in pushed commits. Skipping checks'\n",
+            "    exit 0\n",
+            "fi\n",
+        ),
+        RUST_PATHSPEC
+    )
+}
+
+// Detect rebase, merge or cherry-pick in progress. Git creates commits one by one in these states
+// and running all checks on each of them is slow. Only commands listed in `in-progress.commands` are
+// run in these states.
+fn detect_in_progress(run_some: bool) -> String {
+    let mut s = concat!(
+        "\ncargo_husky_git_dir=\"$(git rev-parse --git-dir)\"\n",
+        "if [ -d \"$cargo_husky_git_dir/rebase-merge\" ] || [ -d \"$cargo_husky_git_dir/rebase-apply\" ]; then\n",
+        "    cargo_husky_in_progress=rebase\n",
+        "elif [ -f \"$cargo_husky_git_dir/MERGE_HEAD\" ]; then\n",
+        "    cargo_husky_in_progress=merge\n",
+        "elif [ -f \"$cargo_husky_git_dir/CHERRY_PICK_HEAD\" ]; then\n",
+        "    cargo_husky_in_progress=cherry-pick\n",
+        "else\n",
+        "    cargo_husky_in_progress=\n",
+        "fi\n",
+    )
+    .to_string();
+    if !run_some {
+        s += concat!(
+            "if [ -n \"$cargo_husky_in_progress\" ]; then\n",
+            "    echo \"cargo-husky: Skipping checks during $cargo_husky_in_progress\"\n",
+            "    exit 0\n",
+            "fi\n",
+        );
+    }
+    s
+}
+
+fn named(name: &str, mut step: Step) -> Step {
+    step.name = name.to_string();
+    step
+}
+
+// Command defined by `commands.<name>.run`. It can also replace the built-in command.
+fn custom_step(name: &str, config: &Config) -> Result<Option<Step>> {
+    let key = format!("commands.{}.run", name);
+    let line = match config.get_str(&key)? {
+        Some(line) => line,
+        None => return Ok(None),
+    };
+    match split_words(line) {
+        None => Err(Error::InvalidConfig(
+            key,
+            "command line",
+            "string with unclosed quote",
+        )),
+        Some(words) if words.is_empty() => {
+            Err(Error::InvalidConfig(key, "command line", "empty string"))
+        }
+        Some(words) => Ok(Some(named(name, Step::new(&words)))),
+    }
+}
+
+// Names of commands defined by `commands.<name>.run` other than built-in commands
+fn custom_commands(config: &Config) -> Result<Vec<&str>> {
+    let mut names = vec![];
+    for name in config.get_table_keys("commands")?.unwrap_or_default() {
+        if !COMMANDS.contains(&name) && config.get_str(&format!("commands.{}.run", name))?.is_some()
+        {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+// Apply settings in `[package.metadata.husky.commands.{name}]` to the step
+fn configure_step(step: &mut Step, config: &Config) -> Result<()> {
+    let key = format!("commands.{}.allow-failure", step.name);
+    step.allow_failure = config.get_bool(&key)?.unwrap_or(false);
+    let key = format!("commands.{}.timeout", step.name);
+    step.timeout = match config.get_int(&key)? {
+        Some(secs) if secs <= 0 => {
+            return Err(Error::InvalidConfig(
+                key,
+                "positive integer",
+                "non-positive integer",
+            ))
+        }
+        Some(secs) => Some(secs as u64),
+        None => None,
+    };
+    Ok(())
+}
+
+/// Names of commands which hooks can run, in the order they are run.
+pub const COMMANDS: &[&str] = &[
+    "nextest",
+    "test",
+    "doctest",
+    "bench",
+    "check",
+    "msrv",
+    "clippy",
+    "fmt",
+    "sort",
+    "typos",
+    "doc",
+    "audit",
+    "deny",
+    "machete",
+    "udeps",
+    "miri",
+    "hack",
+    "semver-checks",
+    "publish",
+    "just",
+    "make",
+    "cargo-make",
+];
+
+// Names of commands enabled by `run-cargo-*` features
+fn enabled_commands(hook: &str) -> Vec<&'static str> {
+    let mut names = vec![];
+    // Only fast doc tests are run before each commit. Other hooks such as pre-push run all tests.
+    if cfg!(feature = "run-cargo-doctest") && hook == "pre-commit" {
+        names.push("doctest");
+    } else if cfg!(feature = "run-cargo-nextest") {
+        names.push("nextest");
+    } else if cfg!(feature = "run-cargo-test") {
+        names.push("test");
+    }
+    if cfg!(feature = "run-cargo-bench") {
+        names.push("bench");
+    }
+    if cfg!(feature = "run-cargo-check") {
+        names.push("check");
+    }
+    if cfg!(feature = "run-cargo-msrv") {
+        names.push("msrv");
+    }
+    if cfg!(feature = "run-cargo-clippy") {
+        names.push("clippy");
+    }
+    if cfg!(feature = "run-cargo-fmt") {
+        names.push("fmt");
+    }
+    if cfg!(feature = "run-cargo-sort") {
+        names.push("sort");
+    }
+    if cfg!(feature = "run-typos") {
+        names.push("typos");
+    }
+    if cfg!(feature = "run-cargo-doc") {
+        names.push("doc");
+    }
+    if cfg!(feature = "run-cargo-audit") {
+        names.push("audit");
+    }
+    if cfg!(feature = "run-cargo-deny") {
+        names.push("deny");
+    }
+    if cfg!(feature = "run-cargo-machete") {
+        names.push("machete");
+    }
+    if cfg!(feature = "run-cargo-udeps") {
+        names.push("udeps");
+    }
+    // Miri is too slow to run on each commit
+    if cfg!(feature = "run-cargo-miri") && hook == "pre-push" {
+        names.push("miri");
+    }
+    // Checking all combinations of features takes long time
+    if cfg!(feature = "run-cargo-hack") && hook == "pre-push" {
+        names.push("hack");
+    }
+    // Checking semver compatibility is only meaningful before sharing the changes
+    if cfg!(feature = "run-cargo-semver-checks") && hook == "pre-push" {
+        names.push("semver-checks");
+    }
+    if cfg!(feature = "run-cargo-publish") && hook == "pre-push" {
+        names.push("publish");
+    }
+    if cfg!(feature = "run-just") {
+        names.push("just");
+    }
+    if cfg!(feature = "run-make") {
+        names.push("make");
+    }
+    if cfg!(feature = "run-cargo-make") {
+        names.push("cargo-make");
+    }
+    names
+}
+
+// Hooks which run no command unless `<hook>.commands` is configured. Commands enabled by features
+// are too slow for them.
+fn commands_opt_in(hook: &str) -> bool {
+    matches!(
+        hook,
+        "prepare-commit-msg" | "pre-rebase" | "post-checkout" | "post-rewrite" | "applypatch-msg"
+    )
+}
+
+// Names of commands run by the hook. `<hook>.commands` overrides the commands enabled by features
+// and custom commands.
+fn hook_commands<'a>(hook: &str, config: &'a Config) -> Result<Vec<&'a str>> {
+    let key = format!("{}.commands", hook);
+    let custom = custom_commands(config)?;
+    let names = match config.get_str_array(&key)? {
+        Some(names) => names,
+        None if commands_opt_in(hook) => return Ok(vec![]),
+        None => {
+            let mut names = enabled_commands(hook);
+            names.extend(custom);
+            return Ok(names);
+        }
+    };
+    for name in &names {
+        if !COMMANDS.contains(name) && !custom.contains(name) {
+            return Err(Error::UnknownCommand(key, name.to_string()));
+        }
+    }
+    Ok(names)
+}
+
+// Commands which build crates. Other commands don't accept options such as `--locked`
+const BUILD_COMMANDS: &[&str] = &[
+    "nextest", "test", "doctest", "bench", "check", "msrv", "clippy", "doc", "udeps", "miri",
+];
+
+// Commands which accept `--all-targets` to build examples, tests and benchmarks
+const ALL_TARGETS_COMMANDS: &[&str] = &["nextest", "test", "check", "msrv", "clippy"];
+
+// Options of feature sets which the command is run with. `None` means default features.
+// `commands.<name>.feature-matrix` overrides `feature-matrix`.
+fn feature_matrix(name: &str, config: &Config) -> Result<Vec<Option<&'static str>>> {
+    let key = format!("commands.{}.feature-matrix", name);
+    let (key, sets) = match config.get_str_array(&key)? {
+        Some(sets) => (key, sets),
+        None => match config.get_str_array("feature-matrix")? {
+            Some(sets) => ("feature-matrix".to_string(), sets),
+            None => return Ok(vec![None]),
+        },
+    };
+    if sets.is_empty() {
+        return Err(Error::InvalidConfig(key, "non-empty array", "empty array"));
+    }
+    sets.iter()
+        .map(|set| match *set {
+            "default" => Ok(None),
+            "no-default-features" => Ok(Some("--no-default-features")),
+            "all-features" => Ok(Some("--all-features")),
+            _ => Err(Error::InvalidConfig(
+                key.clone(),
+                "\"default\", \"no-default-features\" or \"all-features\"",
+                "other string",
+            )),
+        })
+        .collect()
+}
+
+fn command_step(name: &str, hook: &str, staged_only: bool, config: &Config) -> Result<Step> {
+    let all = for_all(name, config)?;
+    let step = match name {
+        "nextest" => {
+            // Fall back to `cargo test` when cargo-nextest is not installed
+            let mut nextest = external_step(
+                workspace_step(&["cargo", "nextest", "run"], all),
+                "cargo-nextest",
+            );
+            nextest.fallback = Some(cargo_step(&["cargo", "test"], &[], all).args);
+            nextest
+        }
+        "test" => cargo_step(&["cargo", "test"], &[], all),
+        "doctest" => cargo_step(&["cargo", "test", "--doc"], &[], all),
+        // Benchmarks are only compiled since nothing else builds them
+        "bench" => cargo_step(&["cargo", "bench", "--no-run"], &[], all),
+        "check" => cargo_step(&["cargo", "check"], &[], all),
+        "msrv" => msrv_step(config)?,
+        "clippy" => cargo_step(&["cargo", "clippy"], &["-D", "warnings"], all),
+        "fmt" if staged_only => rustfmt_step(config, true),
+        "fmt" => cargo_step(&["cargo", "fmt"], &["--check"], all),
+        "sort" => sort_step(staged_only, all),
+        "typos" => typos_step(hook),
+        "doc" => {
+            let mut doc = workspace_step(&["cargo", "doc", "--no-deps"], all);
+            doc.env
+                .push(("RUSTDOCFLAGS".to_string(), "-D warnings".to_string()));
+            doc
+        }
+        "audit" => external_step(Step::new(&["cargo", "audit"]), "cargo-audit"),
+        "deny" => external_step(Step::new(&["cargo", "deny", "check"]), "cargo-deny"),
+        "machete" => external_step(Step::new(&["cargo", "machete"]), "cargo-machete"),
+        "udeps" => udeps_step(config)?,
+        "miri" => miri_step(config)?,
+        "hack" => hack_step(config)?,
+        "semver-checks" => semver_checks_step(config)?,
+        "just" | "make" | "cargo-make" => task_step(name, hook, config)?,
+        "publish" => {
+            // Packaging errors only matter on releases
+            let mut publish = Step::new(&["cargo", "publish", "--dry-run", "--allow-dirty"]);
+            publish.release_only = hook == "pre-push";
+            publish
+        }
+        _ => unreachable!("unknown command {}", name),
+    };
+    let mut step = named(name, step);
+    if ALL_TARGETS_COMMANDS.contains(&name)
+        && config
+            .get_bool("all-targets")?
+            .unwrap_or(cfg!(feature = "all-targets"))
+    {
+        add_cargo_flags(&mut step.args, &["--all-targets"]);
+        if let Some(fallback) = &mut step.fallback {
+            add_cargo_flags(fallback, &["--all-targets"]);
+        }
+    }
+    if BUILD_COMMANDS.contains(&name) {
+        let flags = lock_flags(config)?;
+        add_cargo_flags(&mut step.args, &flags);
+        if let Some(fallback) = &mut step.fallback {
+            add_cargo_flags(fallback, &flags);
+        }
+    }
+    Ok(step)
+}
+
+/// Commands run by the hook.
+pub fn steps(hook: &str, config: &Config) -> Result<Vec<Step>> {
+    let staged_only = cfg!(feature = "staged-only") && hook == "pre-commit";
+    let autofix = cfg!(feature = "autofix") && hook == "pre-commit";
+    let mut steps = vec![];
+    for name in hook_commands(hook, config)? {
+        // Formatting was already fixed by autofix
+        if name == "fmt" && autofix {
+            continue;
+        }
+        // Custom command lines are run as written
+        let step = match custom_step(name, config)? {
+            Some(step) => {
+                steps.push(step);
+                continue;
+            }
+            None => command_step(name, hook, staged_only, config)?,
+        };
+        if !BUILD_COMMANDS.contains(&name) {
+            steps.push(step);
+            continue;
+        }
+        // The command is run for each feature set
+        for flag in feature_matrix(name, config)? {
+            let mut step = step.clone();
+            if let Some(flag) = flag {
+                add_cargo_flags(&mut step.args, &[flag]);
+                if let Some(fallback) = &mut step.fallback {
+                    add_cargo_flags(fallback, &[flag]);
+                }
+            }
+            steps.push(step);
+        }
+    }
+    for step in &mut steps {
+        configure_step(step, config)?;
+    }
+    if cfg!(feature = "skip-in-progress") && hook == "pre-commit" {
+        let commands = config
+            .get_str_array("in-progress.commands")?
+            .unwrap_or_default();
+        for step in &mut steps {
+            step.skip_in_progress = !commands.contains(&step.name.as_str());
+        }
+    }
+    Ok(steps)
+}
+
+// Policy on command failures. `<hook>.policy` overrides `policy` for the hook.
+fn fail_fast(hook: &str, config: &Config) -> Result<bool> {
+    let key = format!("{}.policy", hook);
+    let (key, policy) = match config.get_str(&key)? {
+        Some(policy) => (key, Some(policy)),
+        None => ("policy".to_string(), config.get_str("policy")?),
+    };
+    match policy {
+        None | Some("fail-fast") => Ok(true),
+        Some("run-all") => Ok(false),
+        Some(_) => Err(Error::InvalidConfig(
+            key,
+            "\"fail-fast\" or \"run-all\"",
+            "other string",
+        )),
+    }
+}
+
+// Message shown when the hook fails. `<hook>.failure-message` overrides `failure-message`.
+fn failure_message<'a>(hook: &str, config: &'a Config) -> Result<Option<&'a str>> {
+    match config.get_str(&format!("{}.failure-message", hook))? {
+        Some(msg) => Ok(Some(msg)),
+        None => config.get_str("failure-message"),
+    }
+}
+
+// Function to show the failure message, called with the exit status of the hook on exit
+fn failure_epilogue(msg: &str) -> String {
+    format!(
+        concat!(
+            "\ncargo_husky_epilogue() {{\n",
+            "    if [ \"$1\" -ne 0 ]; then\n",
+            "        printf '\\n%s\\n' {} >&2\n",
+            "    fi\n",
+            "}}\n",
+        ),
+        quote(msg)
+    )
+}
+
+// EXIT trap which runs `cleanup` and then shows the failure message when `epilogue` is set. The
+// exit status is saved before `cleanup` overwrites `$?`.
+fn exit_trap(cleanup: &str, epilogue: bool) -> String {
+    match (cleanup.is_empty(), epilogue) {
+        (_, false) => format!("trap {} EXIT\n", quote(cleanup)),
+        (true, true) => "trap 'cargo_husky_epilogue \"$?\"' EXIT\n".to_string(),
+        (false, true) => format!(
+            "trap {} EXIT\n",
+            quote(&format!(
+                "cargo_husky_status=$?; {}; cargo_husky_epilogue \"$cargo_husky_status\"",
+                cleanup
+            ))
+        ),
+    }
+}
+
+// Output of commands is shown only when they fail with `output = "quiet"`
+fn quiet(config: &Config) -> Result<bool> {
+    match config.get_str("output")? {
+        None | Some("full") => Ok(false),
+        Some("quiet") => Ok(true),
+        Some(_) => Err(Error::InvalidConfig(
+            "output".to_string(),
+            "\"full\" or \"quiet\"",
+            "other string",
+        )),
+    }
+}
+
+// Run all steps even if some of them fail, and report the failed commands at the end. Each step
+// is run in subshell so that `exit` in it does not stop the hook.
+fn run_all_steps(steps: &[Step], quiet: bool) -> String {
+    let mut s = "\ncargo_husky_failed=\n".to_string();
+    for step in steps {
+        s += &format!(
+            "if ! ({}\n); then\n    cargo_husky_failed=\"$cargo_husky_failed\n    {}\"\nfi\n",
+            step_script(step, quiet),
+            escape_double_quoted(&step.display())
+        );
+    }
+    s += concat!(
+        "if [ -n \"$cargo_husky_failed\" ]; then\n",
+        "    echo \"cargo-husky: Some commands failed:$cargo_husky_failed\" >&2\n",
+        "    exit 1\n",
+        "fi\n",
+    );
+    s
+}
+
+// Run steps with the policy and show the summary when all of them passed
+fn run_steps(steps: &[Step], fail_fast: bool, quiet: bool) -> String {
+    if steps.is_empty() {
+        return String::new();
+    }
+    let mut s = if fail_fast {
+        steps.iter().map(|step| step_script(step, quiet)).collect()
+    } else {
+        run_all_steps(steps, quiet)
+    };
+    s += "\ncargo_husky_color 32 '[cargo-husky] All commands passed'\n";
+    s
+}
+
+// Define the color function at the beginning of the script when it is used
+fn with_color_func(s: String) -> String {
+    if s.contains("cargo_husky_color ") {
+        COLOR_FUNC.to_string() + &s
+    } else {
+        s
+    }
+}
+
+/// Plan of commands run by `cargo husky run` with `native-runner` feature.
+pub fn plan(hook: &str, config: &Config) -> Result<Plan> {
+    let jobs = match config.get_int("jobs")? {
+        Some(jobs) if jobs <= 0 => {
+            return Err(Error::InvalidConfig(
+                "jobs".to_string(),
+                "positive integer",
+                "non-positive integer",
+            ))
+        }
+        Some(jobs) => jobs as usize,
+        None => 1,
+    };
+    Ok(Plan {
+        jobs,
+        fail_fast: fail_fast(hook, config)?,
+        quiet: quiet(config)?,
+        timings: config.get_bool("timings")?.unwrap_or(false),
+        timings_log: config.get_bool("timings-log")?.unwrap_or(false),
+        affected_only: config.get_bool("affected-only")?.unwrap_or(false),
+        steps: steps(hook, config)?,
+    })
+}
+
+/// Render the hook script which runs user hook scripts in `<hook>.d` directory in lexical order.
+/// Arguments and stdin of the hook are passed to each script.
+pub fn render_dispatcher(hook: &str, config: &Config) -> Result<String> {
+    let (read_stdin, redirect) = if hook_reads_stdin(hook) {
+        (
+            concat!(
+                "hook_stdin=\"$(mktemp)\"\n",
+                "trap 'rm -f \"$hook_stdin\"' EXIT\n",
+                "cat > \"$hook_stdin\"\n",
+            ),
+            " < \"$hook_stdin\"",
+        )
+    } else {
+        ("", "")
+    };
+    Ok(format!(
+        r#"{}
+#
+# This hook was set by cargo-husky v{}: {}
+# Run user hook scripts in {}.d directory in lexical order
+#
+
+set -eu
+{}
+for script in "$(dirname "$0")/{}.d"/*; do
+    if [ -f "$script" ] && [ -x "$script" ]; then
+        "$script" "$@"{}
+    fi
+done
+"#,
+        shebang(config)?,
+        env!("CARGO_PKG_VERSION"),
+        env!("CARGO_PKG_HOMEPAGE"),
+        hook,
+        read_stdin,
+        hook,
+        redirect
+    ))
+}
+
+/// Render the hook script registered in `.hg/hgrc` of a Mercurial repository. Only commands of the
+/// hook are run since other checks depend on Git. Commands which check staged files are also
+/// skipped.
+pub fn render_mercurial(hook: &str, config: &Config) -> Result<String> {
+    let shebang = shebang(config)?;
+    let mut s = String::new();
+    if let Some(msg) = failure_message(hook, config)? {
+        s += &failure_epilogue(msg);
+        s += &exit_trap("", true);
+    }
+    let reminders = reminders(hook, config)?;
+    if !reminders.is_empty() {
+        s += &show_reminders(&reminders);
+    }
+    s += &change_working_dir(config)?;
+    let steps = steps(hook, config)?
+        .into_iter()
+        .filter(|step| !step.staged)
+        .collect::<Vec<_>>();
+    if !steps.is_empty() {
+        s += locate_cargo();
+    }
+    s += &exports(config)?;
+    s += &run_steps(&steps, fail_fast(hook, config)?, quiet(config)?);
+    Ok(format!(
+        r#"{}
+#
+# This hook was set by cargo-husky v{}: {}
+# Run commands of {} hook in Mercurial repository
+{}{}
+#
+
+{}
+{}
+"#,
+        shebang,
+        env!("CARGO_PKG_VERSION"),
+        env!("CARGO_PKG_HOMEPAGE"),
+        hook,
+        CONFIG_HASH_HEADER,
+        config.hash(),
+        shell_options(&shebang),
+        with_color_func(s)
+    ))
+}
+
+/// Render the block put in hook scripts of other hook managers such as npm husky or lefthook. The
+/// block runs the hook script `name` installed by cargo-husky in `.git/hooks`.
+pub fn render_managed_block(name: &str) -> String {
+    format!(
+        r#"{}
+# This block was set by cargo-husky v{}: {}
+cargo_husky_hook="$(git rev-parse --git-common-dir)/hooks/{}"
+if [ -x "$cargo_husky_hook" ]; then
+    "$cargo_husky_hook" "$@" || exit $?
+fi
+{}
+"#,
+        MANAGED_BLOCK_BEGIN,
+        env!("CARGO_PKG_VERSION"),
+        env!("CARGO_PKG_HOMEPAGE"),
+        name,
+        MANAGED_BLOCK_END
+    )
+}
+
+/// Render the sh wrapper script which runs Windows user hook script `<hook>.<ext>` (`ps1`, `cmd` or
+/// `bat`). Git for Windows runs only hooks without extension with its sh.
+pub fn render_windows_runner(hook: &str, ext: &str, config: &Config) -> Result<String> {
+    let command = match ext {
+        "ps1" => "powershell.exe -NoProfile -ExecutionPolicy Bypass -File \"$0.ps1\"".to_string(),
+        // `//c` is not converted to a path by MSYS. cmd.exe needs the path with backslashes
+        _ => format!("cmd.exe //c \"$(cygpath -w \"$0.{}\")\"", ext),
+    };
+    Ok(format!(
+        r#"{}
+#
+# This hook was set by cargo-husky v{}: {}
+# Run user hook script {}.{} on Windows
+#
+
+exec {} "$@"
+"#,
+        shebang(config)?,
+        env!("CARGO_PKG_VERSION"),
+        env!("CARGO_PKG_HOMEPAGE"),
+        hook,
+        ext,
+        command
+    ))
+}
+
+/// Render the hook script generated for the hook. `chained` and `user` mean `{hook}.local` and
+/// `{hook}.user` scripts are run before checks.
+pub fn render(hook: &str, chained: bool, user: bool, config: &Config) -> Result<String> {
+    let shebang = shebang(config)?;
+    let mut s = String::new();
+    let protected = match hook {
+        "pre-push" => config.get_str_array("protected-branches")?,
+        "pre-commit" if cfg!(feature = "protect-branches") => Some(
+            config
+                .get_str_array("protected-branches")?
+                .unwrap_or_else(|| vec!["main", "master"]),
+        ),
+        "pre-rebase" => Some(
+            config
+                .get_str_array("protected-branches")?
+                .unwrap_or_else(|| vec!["main", "master"]),
+        ),
+        _ => None,
+    };
+    let skip_unchanged = cfg!(feature = "skip-unchanged") && hook == "pre-push";
+    // Input from stdin is saved to a file since it is read several times
+    let release = hook == "pre-push" && steps(hook, config)?.iter().any(|s| s.release_only);
+    let version_tag = cfg!(feature = "check-version-tag") && hook == "pre-push";
+    let reads_stdin = hook_reads_stdin(hook)
+        && (chained || user || protected.is_some() || skip_unchanged || release || version_tag);
+    let epilogue = match failure_message(hook, config)? {
+        Some(msg) => {
+            s += &failure_epilogue(msg);
+            true
+        }
+        None => false,
+    };
+    if reads_stdin {
+        s += "\nhook_stdin=\"$(mktemp)\"\n";
+        s += &exit_trap("rm -f \"$hook_stdin\"", epilogue);
+        s += "cat > \"$hook_stdin\"\n";
+    } else if epilogue {
+        s += &exit_trap("", true);
+    }
+    if chained {
+        s += "\n# Run the hook which had been put before cargo-husky (chain-hooks feature)\n";
+        if reads_stdin {
+            s += "\"$0.local\" \"$@\" < \"$hook_stdin\"\n";
+        } else {
+            s += "\"$0.local\" \"$@\"\n";
+        }
+    }
+    if user {
+        s += "\n# Run the user hook script (combine-hooks feature)\n";
+        if reads_stdin {
+            s += "\"$0.user\" \"$@\" < \"$hook_stdin\"\n";
+        } else {
+            s += "\"$0.user\" \"$@\"\n";
+        }
+    }
+    // The path of the commit message file is relative to the directory where Git runs the hook
+    if hook == "prepare-commit-msg" {
+        let pattern = config
+            .get_str("issue-id.pattern")?
+            .unwrap_or("[A-Z][A-Z0-9]+-[0-9]+");
+        s += &inject_issue_id(pattern);
+    }
+    // Hooks put by others are not covered by the consent
+    if cfg!(feature = "ask-consent") {
+        s += &ask_consent(config)?;
+    }
+    // Git does not always run hooks at the root of the working tree (e.g. when `GIT_DIR` is set).
+    // Cargo commands would find another package and paths of staged files would be wrong then.
+    // `$0` may be relative so hooks above are run before changing the directory.
+    s += concat!(
+        "\nif cargo_husky_root=\"$(git rev-parse --show-toplevel 2>/dev/null)\"; then\n",
+        "    cd \"$cargo_husky_root\" || exit\n",
+        "fi\n",
+    );
+    // Hooks which run no command by default don't require cargo-husky binary
+    let native = cfg!(feature = "native-runner")
+        && !(commands_opt_in(hook) && steps(hook, config)?.is_empty());
+    let refresh = matches!(hook, "post-checkout" | "post-rewrite");
+    let uses_cargo = native
+        || refresh
+        || version_tag
+        || (hook == "pre-commit"
+            && cfg!(any(
+                feature = "check-secrets",
+                feature = "check-lockfile",
+                feature = "check-denylist",
+                feature = "autofix"
+            )))
+        || !steps(hook, config)?.is_empty();
+    if uses_cargo {
+        s += locate_cargo();
+    }
+    if cfg!(feature = "skip-in-progress") && hook == "pre-commit" {
+        let commands = config
+            .get_str_array("in-progress.commands")?
+            .unwrap_or_default();
+        s += &detect_in_progress(!commands.is_empty());
+    }
+    if hook == "pre-commit" || hook == "pre-push" {
+        if let Some(pattern) = config.get_str("branch-name.pattern")? {
+            s += &validate_branch_name(pattern, config.get_str("branch-name.message")?);
+        }
+    }
+    if let Some(branches) = protected {
+        if branches.is_empty() {
+            // Nothing to protect
+        } else if hook == "pre-push" {
+            s += &refuse_protected_push(&branches);
+        } else if hook == "pre-rebase" {
+            s += &refuse_protected_rebase(&branches);
+        } else {
+            s += &refuse_protected_commit(&branches);
+        }
+    }
+    if cfg!(feature = "check-markers") && hook == "pre-commit" {
+        let patterns = config
+            .get_str_array("check-markers.patterns")?
+            .unwrap_or_else(|| vec!["<<<<<<<", ">>>>>>>", "dbg!(", "todo!()"]);
+        if !patterns.is_empty() {
+            s += &check_markers(&patterns);
+        }
+    }
+    if cfg!(feature = "check-large-files") && hook == "pre-commit" {
+        let key = "large-files.max-size";
+        let max_size = match config.get_int(key)? {
+            Some(size) if size < 0 => {
+                return Err(Error::InvalidConfig(
+                    key.to_string(),
+                    "non-negative integer",
+                    "negative integer",
+                ))
+            }
+            Some(size) => size,
+            None => 5 * 1024 * 1024,
+        };
+        let allow = config
+            .get_str_array("large-files.allow")?
+            .unwrap_or_default();
+        s += &check_large_files(max_size, &allow);
+    }
+    if cfg!(feature = "check-secrets") && hook == "pre-commit" {
+        s += &external_cmd("husky", "cargo-husky", "cargo husky run secrets");
+    }
+    if cfg!(feature = "check-lockfile") && hook == "pre-commit" {
+        s += &external_cmd("husky", "cargo-husky", "cargo husky run lockfile");
+    }
+    if cfg!(feature = "check-denylist") && hook == "pre-commit" {
+        let patterns = config
+            .get_str_array("denied-files.patterns")?
+            .unwrap_or_else(|| vec!["*.pem", ".env", "target/"]);
+        if !patterns.is_empty() {
+            let mut c = "cargo husky run denylist".to_string();
+            for pattern in &patterns {
+                c += " ";
+                c += &quote(pattern);
+            }
+            s += &external_cmd("husky", "cargo-husky", &c);
+        }
+    }
+    if release {
+        let branches = config
+            .get_str_array("publish.release-branches")?
+            .unwrap_or_default();
+        s += &detect_release_push(&branches);
+    }
+    if version_tag {
+        let manifest = config
+            .get_str("version-tag.manifest")?
+            .unwrap_or("Cargo.toml");
+        let changelog = config
+            .get_str("version-tag.changelog")?
+            .unwrap_or("CHANGELOG.md");
+        let mut c = format!("cargo husky run version-tag {}", quote(manifest));
+        if !changelog.is_empty() {
+            c += " ";
+            c += &quote(changelog);
+        }
+        s += &require_cmd("husky", "cargo-husky");
+        s += &run_line(&c, &format!("{} < \"$hook_stdin\"", c));
+    }
+    if skip_unchanged {
+        s += &skip_unchanged_push();
+    }
+    let reminders = reminders(hook, config)?;
+    if !reminders.is_empty() {
+        s += &show_reminders(&reminders);
+    }
+    // Checks above see the whole repository. Commands and checks below only see `working-dir`.
+    s += &change_working_dir(config)?;
+    let staged_only = cfg!(feature = "staged-only") && hook == "pre-commit";
+    if staged_only {
+        // Checks are skipped unless files checked by some command are staged
+        let mut pathspec = vec!["*.rs"];
+        let steps = steps(hook, config)?;
+        for p in steps
+            .iter()
+            .filter(|s| s.staged)
+            .flat_map(|s| s.staged_pathspec())
+        {
+            if !pathspec.contains(&p) {
+                pathspec.push(p);
+            }
+        }
+        let what = if pathspec.contains(&"*") {
+            "file"
+        } else if pathspec.len() == 1 {
+            "Rust file"
+        } else {
+            "Rust file or manifest"
+        };
+        s += &format!(
+            "\nif [ -z \"$({} | tr -d '\\0')\" ]; then\n    echo 'cargo-husky: No {} is staged. Skipping checks'\n    exit 0\nfi\n",
+            staged_files(&pathspec),
+            what
+        );
+    }
+    let autofix = cfg!(feature = "autofix") && hook == "pre-commit";
+    if cfg!(feature = "stash-unstaged") && hook == "pre-commit" {
+        // Run checks against the index. Unstaged changes are restored even when checks fail or
+        // the hook is interrupted by a signal. Files staged by autofix are kept in the index.
+        s += "\nif ! git diff --quiet; then\n";
+        s += "    git stash push --keep-index --quiet --message 'cargo-husky: unstaged changes'\n";
+        s += "    cargo_husky_restore_unstaged() {\n";
+        if autofix {
+            s += "        cargo_husky_index=\"$(git write-tree)\"\n";
+        }
+        s += concat!(
+            "        git reset --hard --quiet\n",
+            "        if ! git stash pop --index --quiet; then\n",
+            "            echo \"cargo-husky: Failed to restore unstaged changes. They are saved in 'git stash list'\" >&2\n",
+        );
+        if autofix {
+            s += "        else\n            git read-tree \"$cargo_husky_index\"\n";
+        }
+        s += "        fi\n    }\n    ";
+        s += &exit_trap("cargo_husky_restore_unstaged", epilogue);
+        s += concat!(
+            "    trap 'exit 129' HUP\n",
+            "    trap 'exit 130' INT\n",
+            "    trap 'exit 143' TERM\n",
+            "fi\n",
+        );
+    }
+    s += &exports(config)?;
+    if refresh {
+        s += &refresh_caches(hook);
+    }
+    if autofix {
+        s += &autofix_cmd(config, staged_only)?;
+    }
+    if native {
+        // Commands are run by `cargo husky run` reading the plan written by build script
+        // Nothing is output to stdout since it may be JSON
+        s += &require_cmd("husky", "cargo-husky");
+        s += &format!("\ncargo husky run {}", hook);
+    } else {
+        s += &run_steps(
+            &steps(hook, config)?,
+            fail_fast(hook, config)?,
+            quiet(config)?,
+        );
+    }
+    // No path, time or environment is put in the script. Hooks generated from the same
+    // configuration are identical on any machine so that they can be committed to the repository.
+    Ok(format!(
+        r#"{}
+#
+# This hook was set by cargo-husky v{}: {}
+# Generated by script build.rs of cargo-husky
+{}{}
+#
+
+{}
+{}
+"#,
+        shebang,
+        env!("CARGO_PKG_VERSION"),
+        env!("CARGO_PKG_HOMEPAGE"),
+        CONFIG_HASH_HEADER,
+        config.hash(),
+        shell_options(&shebang),
+        with_color_func(s)
+    ))
+}
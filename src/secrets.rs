@@ -0,0 +1,152 @@
+use error::Result;
+use git;
+use std::path::Path;
+
+/// Comment to suppress a finding on the line.
+pub const ALLOW_COMMENT: &str = "husky:allow-secret";
+
+/// A credential-like string found in added lines of a diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretFinding {
+    pub file: String,
+    pub line: usize,
+    pub kind: &'static str,
+}
+
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "+/=_-".contains(c)
+}
+
+// Shannon entropy in bits per character
+fn entropy(s: &str) -> f64 {
+    let mut counts = [0usize; 256];
+    for b in s.bytes() {
+        counts[b as usize] += 1;
+    }
+    let len = s.len() as f64;
+    counts
+        .iter()
+        .filter(|c| **c > 0)
+        .map(|c| {
+            let p = *c as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+fn has_prefixed_token(token: &str, prefix: &str, len: usize) -> bool {
+    token.starts_with(prefix)
+        && token.len() >= prefix.len() + len
+        && token[prefix.len()..]
+            .chars()
+            .take(len)
+            .all(|c| c.is_ascii_alphanumeric())
+}
+
+fn is_aws_access_key(token: &str) -> bool {
+    ["AKIA", "ASIA"].iter().any(|prefix| {
+        token.starts_with(prefix)
+            && token.len() == 20
+            && token[4..]
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+    })
+}
+
+// Random strings such as API keys. Requiring mixed character classes avoids flagging identifiers
+// and hashes.
+fn is_high_entropy(token: &str) -> bool {
+    token.len() >= 32
+        && token.chars().any(|c| c.is_ascii_uppercase())
+        && token.chars().any(|c| c.is_ascii_lowercase())
+        && token.chars().any(|c| c.is_ascii_digit())
+        && entropy(token) >= 4.5
+}
+
+/// Detect kind of credential in the line.
+pub fn detect_secret(line: &str) -> Option<&'static str> {
+    if line.contains(ALLOW_COMMENT) {
+        return None;
+    }
+    if line.contains("-----BEGIN") && line.contains("PRIVATE KEY-----") {
+        return Some("private key");
+    }
+    for token in line.split(|c| !is_token_char(c)) {
+        if is_aws_access_key(token) {
+            return Some("AWS access key ID");
+        }
+        if has_prefixed_token(token, "ghp_", 36)
+            || has_prefixed_token(token, "gho_", 36)
+            || has_prefixed_token(token, "github_pat_", 22)
+        {
+            return Some("GitHub token");
+        }
+        if ["xoxb-", "xoxa-", "xoxp-", "xoxr-", "xoxs-"]
+            .iter()
+            .any(|p| has_prefixed_token(token, p, 10))
+        {
+            return Some("Slack token");
+        }
+    }
+    line.split(|c| !is_token_char(c))
+        .find(|t| is_high_entropy(t))
+        .map(|_| "high entropy string")
+}
+
+/// Scan lines added by the unified diff (generated with `-U0`).
+pub fn scan_diff(diff: &str) -> Vec<SecretFinding> {
+    let mut findings = vec![];
+    let mut file = String::new();
+    let mut line = 0;
+    let mut header = false;
+    for l in diff.lines() {
+        if l.starts_with("diff --git ") {
+            header = true;
+        } else if header && l.starts_with("+++ ") {
+            file = l
+                .trim_start_matches("+++ ")
+                .trim_start_matches("b/")
+                .to_string();
+        } else if l.starts_with("@@ ") {
+            header = false;
+            // @@ -a,b +c,d @@
+            line = l
+                .split(' ')
+                .nth(2)
+                .and_then(|r| r.trim_start_matches('+').split(',').next())
+                .and_then(|n| n.parse().ok())
+                .unwrap_or(0);
+        } else if !header && l.starts_with('+') {
+            // Checksums in lockfile are high entropy strings
+            if !file.ends_with("Cargo.lock") {
+                if let Some(kind) = detect_secret(&l[1..]) {
+                    findings.push(SecretFinding {
+                        file: file.clone(),
+                        line,
+                        kind,
+                    });
+                }
+            }
+            line += 1;
+        }
+    }
+    findings
+}
+
+/// Scan staged changes of the repository at `dir`.
+pub fn scan_staged(dir: &Path) -> Result<Vec<SecretFinding>> {
+    let out = git::output(
+        dir,
+        &[
+            "-c",
+            "core.quotePath=false",
+            "diff",
+            "--cached",
+            "--no-color",
+            "--no-ext-diff",
+            "-U0",
+            "--diff-filter=ACM",
+        ],
+    )?;
+    Ok(scan_diff(&String::from_utf8_lossy(&out)))
+}
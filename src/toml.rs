@@ -0,0 +1,470 @@
+// Minimal TOML parser to read cargo-husky configuration from Cargo.toml without depending on any
+// crate. Values which are not used for configuration (floats, dates, ...) are kept as raw text.
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+    Other(String),
+}
+
+pub type Table = BTreeMap<String, Value>;
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Integer(_) => "integer",
+            Value::Boolean(_) => "boolean",
+            Value::Array(_) => "array",
+            Value::Table(_) => "table",
+            Value::Other(_) => "value",
+        }
+    }
+}
+
+/// Look up a value by path of keys such as `["package", "metadata", "husky"]`.
+pub fn lookup<'a>(table: &'a Table, path: &[&str]) -> Option<&'a Value> {
+    let (last, parents) = path.split_last()?;
+    let mut table = table;
+    for key in parents {
+        match table.get(*key)? {
+            Value::Table(t) => table = t,
+            _ => return None,
+        }
+    }
+    table.get(*last)
+}
+
+/// Render the string as TOML basic string.
+pub fn string_literal(s: &str) -> String {
+    let mut lit = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => lit.push_str("\\\""),
+            '\\' => lit.push_str("\\\\"),
+            '\n' => lit.push_str("\\n"),
+            '\t' => lit.push_str("\\t"),
+            '\r' => lit.push_str("\\r"),
+            c if c.is_control() => lit.push_str(&format!("\\u{:04X}", c as u32)),
+            c => lit.push(c),
+        }
+    }
+    lit.push('"');
+    lit
+}
+
+pub fn parse(src: &str) -> Result<Table, String> {
+    let mut parser = Parser {
+        chars: src.chars().collect(),
+        pos: 0,
+    };
+    parser.parse().map_err(|msg| {
+        let line = parser.chars[..parser.pos.min(parser.chars.len())]
+            .iter()
+            .filter(|c| **c == '\n')
+            .count()
+            + 1;
+        format!("{} at line {}", msg, line)
+    })
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+type ParseResult<T> = Result<T, String>;
+
+// Descend to the table at `path`, creating intermediate tables. When a value in the path is an
+// array of tables, its last element is used.
+fn table_at<'a>(mut table: &'a mut Table, path: &[String]) -> ParseResult<&'a mut Table> {
+    for key in path {
+        let value = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Table(Table::new()));
+        table = match value {
+            Value::Table(t) => t,
+            Value::Array(elems) => match elems.last_mut() {
+                Some(Value::Table(t)) => t,
+                _ => return Err(format!("Key '{}' is not a table", key)),
+            },
+            _ => return Err(format!("Key '{}' is not a table", key)),
+        };
+    }
+    Ok(table)
+}
+
+fn insert(table: &mut Table, keys: &[String], value: Value) -> ParseResult<()> {
+    let (last, parents) = keys.split_last().unwrap();
+    table_at(table, parents)?.insert(last.clone(), value);
+    Ok(())
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> ParseResult<()> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!("Expected '{}'", c))
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while let Some(' ') | Some('\t') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while let Some(c) = self.peek() {
+                if c == '\n' {
+                    break;
+                }
+                self.pos += 1;
+            }
+        }
+    }
+
+    // Skip whitespaces, newlines and comments
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n') | Some('\r') => self.pos += 1,
+                _ => return,
+            }
+        }
+    }
+
+    fn expect_line_end(&mut self) -> ParseResult<()> {
+        self.skip_spaces();
+        self.skip_comment();
+        self.eat('\r');
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(c) => Err(format!("Unexpected character '{}'", c)),
+        }
+    }
+
+    fn parse(&mut self) -> ParseResult<Table> {
+        let mut root = Table::new();
+        let mut current = vec![];
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                None => return Ok(root),
+                Some('[') => {
+                    self.pos += 1;
+                    let is_array = self.eat('[');
+                    self.skip_spaces();
+                    let path = self.parse_keys()?;
+                    self.expect(']')?;
+                    if is_array {
+                        self.expect(']')?;
+                        let (last, parents) = path.split_last().unwrap();
+                        let parent = table_at(&mut root, parents)?;
+                        let elems = parent
+                            .entry(last.clone())
+                            .or_insert_with(|| Value::Array(vec![]));
+                        match elems {
+                            Value::Array(elems) => elems.push(Value::Table(Table::new())),
+                            _ => return Err(format!("Key '{}' is not an array", last)),
+                        }
+                    } else {
+                        table_at(&mut root, &path)?;
+                    }
+                    current = path;
+                    self.expect_line_end()?;
+                }
+                Some(_) => {
+                    let keys = self.parse_keys()?;
+                    self.expect('=')?;
+                    self.skip_spaces();
+                    let value = self.parse_value()?;
+                    insert(table_at(&mut root, &current)?, &keys, value)?;
+                    self.expect_line_end()?;
+                }
+            }
+        }
+    }
+
+    // Parse dotted keys such as `a."b.c".d`
+    fn parse_keys(&mut self) -> ParseResult<Vec<String>> {
+        let mut keys = vec![];
+        loop {
+            self.skip_spaces();
+            let key = match self.peek() {
+                Some('"') => self.parse_basic_string()?,
+                Some('\'') => self.parse_literal_string()?,
+                _ => {
+                    let start = self.pos;
+                    while let Some(c) = self.peek() {
+                        if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                            self.pos += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                    if start == self.pos {
+                        return Err("Expected a key".to_string());
+                    }
+                    self.chars[start..self.pos].iter().collect()
+                }
+            };
+            keys.push(key);
+            self.skip_spaces();
+            if !self.eat('.') {
+                return Ok(keys);
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> ParseResult<Value> {
+        match self.peek() {
+            Some('"') => {
+                if self.starts_with("\"\"\"") {
+                    self.parse_multiline_basic_string().map(Value::String)
+                } else {
+                    self.parse_basic_string().map(Value::String)
+                }
+            }
+            Some('\'') => {
+                if self.starts_with("'''") {
+                    self.parse_multiline_literal_string().map(Value::String)
+                } else {
+                    self.parse_literal_string().map(Value::String)
+                }
+            }
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_inline_table(),
+            Some(_) => Ok(self.parse_bare_value()),
+            None => Err("Expected a value".to_string()),
+        }
+    }
+
+    fn parse_escape(&mut self, buf: &mut String) -> ParseResult<()> {
+        let c = self.peek().ok_or("Unterminated string")?;
+        self.pos += 1;
+        match c {
+            'n' => buf.push('\n'),
+            't' => buf.push('\t'),
+            'r' => buf.push('\r'),
+            'b' => buf.push('\u{8}'),
+            'f' => buf.push('\u{c}'),
+            '"' => buf.push('"'),
+            '\\' => buf.push('\\'),
+            'u' | 'U' => {
+                let len = if c == 'u' { 4 } else { 8 };
+                if self.pos + len > self.chars.len() {
+                    return Err("Invalid unicode escape".to_string());
+                }
+                let hex: String = self.chars[self.pos..self.pos + len].iter().collect();
+                self.pos += len;
+                let c = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(::std::char::from_u32)
+                    .ok_or("Invalid unicode escape")?;
+                buf.push(c);
+            }
+            c => return Err(format!("Invalid escape sequence '\\{}'", c)),
+        }
+        Ok(())
+    }
+
+    fn parse_basic_string(&mut self) -> ParseResult<String> {
+        self.expect('"')?;
+        let mut buf = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') => return Err("Unterminated string".to_string()),
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(buf);
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    self.parse_escape(&mut buf)?;
+                }
+                Some(c) => {
+                    self.pos += 1;
+                    buf.push(c);
+                }
+            }
+        }
+    }
+
+    fn parse_multiline_basic_string(&mut self) -> ParseResult<String> {
+        self.pos += 3;
+        self.eat('\r');
+        self.eat('\n'); // Newline immediately following the opening delimiter is trimmed
+        let mut buf = String::new();
+        loop {
+            if self.starts_with("\"\"\"") {
+                self.pos += 3;
+                // Up to two quotes are allowed just before the closing delimiter
+                while self.peek() == Some('"') {
+                    self.pos += 1;
+                    buf.push('"');
+                }
+                return Ok(buf);
+            }
+            match self.peek() {
+                None => return Err("Unterminated string".to_string()),
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(' ') | Some('\t') | Some('\r') | Some('\n') => {
+                            // Line ending backslash trims all whitespaces until next non-whitespace
+                            while let Some(' ') | Some('\t') | Some('\r') | Some('\n') = self.peek()
+                            {
+                                self.pos += 1;
+                            }
+                        }
+                        _ => self.parse_escape(&mut buf)?,
+                    }
+                }
+                Some(c) => {
+                    self.pos += 1;
+                    buf.push(c);
+                }
+            }
+        }
+    }
+
+    fn parse_literal_string(&mut self) -> ParseResult<String> {
+        self.expect('\'')?;
+        let mut buf = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') => return Err("Unterminated string".to_string()),
+                Some('\'') => {
+                    self.pos += 1;
+                    return Ok(buf);
+                }
+                Some(c) => {
+                    self.pos += 1;
+                    buf.push(c);
+                }
+            }
+        }
+    }
+
+    fn parse_multiline_literal_string(&mut self) -> ParseResult<String> {
+        self.pos += 3;
+        self.eat('\r');
+        self.eat('\n');
+        let mut buf = String::new();
+        loop {
+            if self.starts_with("'''") {
+                self.pos += 3;
+                while self.peek() == Some('\'') {
+                    self.pos += 1;
+                    buf.push('\'');
+                }
+                return Ok(buf);
+            }
+            match self.peek() {
+                None => return Err("Unterminated string".to_string()),
+                Some(c) => {
+                    self.pos += 1;
+                    buf.push(c);
+                }
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> ParseResult<Value> {
+        self.expect('[')?;
+        let mut elems = vec![];
+        loop {
+            self.skip_blank();
+            if self.eat(']') {
+                return Ok(Value::Array(elems));
+            }
+            elems.push(self.parse_value()?);
+            self.skip_blank();
+            if !self.eat(',') {
+                self.skip_blank();
+                self.expect(']')?;
+                return Ok(Value::Array(elems));
+            }
+        }
+    }
+
+    fn parse_inline_table(&mut self) -> ParseResult<Value> {
+        self.expect('{')?;
+        let mut table = Table::new();
+        self.skip_spaces();
+        if self.eat('}') {
+            return Ok(Value::Table(table));
+        }
+        loop {
+            let keys = self.parse_keys()?;
+            self.expect('=')?;
+            self.skip_spaces();
+            let value = self.parse_value()?;
+            insert(&mut table, &keys, value)?;
+            self.skip_spaces();
+            if self.eat('}') {
+                return Ok(Value::Table(table));
+            }
+            self.expect(',')?;
+            self.skip_spaces();
+        }
+    }
+
+    // Booleans, integers, floats and dates
+    fn parse_bare_value(&mut self) -> Value {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if matches!(c, ',' | ']' | '}' | '#' | '\n' | '\r') {
+                break;
+            }
+            self.pos += 1;
+        }
+        let raw = self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .trim()
+            .to_string();
+        match raw.as_str() {
+            "true" => Value::Boolean(true),
+            "false" => Value::Boolean(false),
+            _ => match raw.replace('_', "").parse() {
+                Ok(i) => Value::Integer(i),
+                Err(_) => Value::Other(raw),
+            },
+        }
+    }
+}
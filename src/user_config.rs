@@ -0,0 +1,135 @@
+use error::{Error, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use toml::{self, Table, Value};
+
+/// Configuration of each developer in `~/.config/cargo-husky/config.toml`. It controls in which
+/// repositories hooks are installed across all projects depending on cargo-husky.
+#[derive(Debug)]
+pub struct UserConfig {
+    // `install = false` disables installing hooks in all repositories
+    install: bool,
+    // Hooks are installed only in repositories matching any of these patterns when not empty
+    allow: Vec<String>,
+    // Hooks are never installed in repositories matching any of these patterns
+    deny: Vec<String>,
+}
+
+/// Path of the user config. `$XDG_CONFIG_HOME` is respected.
+pub fn user_config_path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => home_dir()?.join(".config"),
+    };
+    Some(dir.join("cargo-husky").join("config.toml"))
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+}
+
+fn get_str_array(table: &Table, key: &str) -> std::result::Result<Vec<String>, String> {
+    let elems = match table.get(key) {
+        None => return Ok(vec![]),
+        Some(Value::Array(elems)) => elems,
+        Some(_) => return Err(format!("'{}' must be an array of strings", key)),
+    };
+    elems
+        .iter()
+        .map(|e| match e {
+            Value::String(s) => Ok(s.clone()),
+            _ => Err(format!("'{}' must be an array of strings", key)),
+        })
+        .collect()
+}
+
+// Match the whole string with `*` and `?` wildcards. `*` also matches `/` so that `~/work/*` matches
+// all repositories under the directory.
+fn wildcard_match(pat: &[char], s: &[char]) -> bool {
+    match pat.split_first() {
+        None => s.is_empty(),
+        Some(('*', rest)) => (0..=s.len()).any(|i| wildcard_match(rest, &s[i..])),
+        Some(('?', rest)) => !s.is_empty() && wildcard_match(rest, &s[1..]),
+        Some((c, rest)) => s.first() == Some(c) && wildcard_match(rest, &s[1..]),
+    }
+}
+
+// Paths are compared with `/` separators. `~/` at the start of a pattern is the home directory.
+fn normalize(s: &str) -> String {
+    let s = s.replace('\\', "/");
+    match (s.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => format!(
+            "{}/{}",
+            home.to_string_lossy()
+                .replace('\\', "/")
+                .trim_end_matches('/'),
+            rest
+        ),
+        _ => s,
+    }
+}
+
+fn matches(pattern: &str, root: &str, remotes: &[String]) -> bool {
+    let pattern = normalize(pattern).chars().collect::<Vec<_>>();
+    let matched = |s: &str| wildcard_match(&pattern, &s.chars().collect::<Vec<_>>());
+    matched(root) || remotes.iter().any(|r| matched(r))
+}
+
+/// URLs of the remotes of the repository. Empty when they cannot be read.
+pub fn remote_urls(root: &Path) -> Vec<String> {
+    let out = match Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["config", "--get-regexp", r"^remote\..*\.url$"])
+        .output()
+    {
+        Ok(out) => out,
+        Err(_) => return vec![],
+    };
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|l| l.split_once(' ').map(|(_, url)| url.to_string()))
+        .collect()
+}
+
+impl UserConfig {
+    pub fn load(path: &Path) -> Result<UserConfig> {
+        let src = fs::read_to_string(path)?;
+        UserConfig::parse(&src).map_err(|msg| Error::UserConfig(path.to_owned(), msg))
+    }
+
+    pub fn parse(src: &str) -> std::result::Result<UserConfig, String> {
+        let root = toml::parse(src)?;
+        let install = match root.get("install") {
+            None => true,
+            Some(Value::Boolean(b)) => *b,
+            Some(_) => return Err("'install' must be a boolean".to_string()),
+        };
+        Ok(UserConfig {
+            install,
+            allow: get_str_array(&root, "allow")?,
+            deny: get_str_array(&root, "deny")?,
+        })
+    }
+
+    /// Reason why hooks must not be installed in the repository at `root` whose remotes are
+    /// `remotes`. `None` means they can be installed.
+    pub fn deny_reason(&self, root: &Path, remotes: &[String]) -> Option<String> {
+        if !self.install {
+            return Some("installing hooks is disabled by 'install = false'".to_string());
+        }
+        let root = normalize(&root.to_string_lossy());
+        if let Some(pat) = self.deny.iter().find(|p| matches(p, &root, remotes)) {
+            return Some(format!("the repository matches '{}' in 'deny'", pat));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|p| matches(p, &root, remotes)) {
+            return Some("the repository matches no pattern in 'allow'".to_string());
+        }
+        None
+    }
+}
@@ -0,0 +1,283 @@
+use config::{stable_hash, Config};
+use error::{Error, Result};
+use hook::{is_managed_hook, merged_hook_path, write_file_if_changed};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{self, string_literal, Table, Value};
+
+// Bumped when the format of install records changes incompatibly
+const RECORD_FORMAT: i64 = 1;
+
+/// The build script records the installed hooks in `.git/hooks/cargo-husky/installed.toml`.
+pub fn record_path(hooks_dir: &Path) -> PathBuf {
+    hooks_dir.join("cargo-husky").join("installed.toml")
+}
+
+/// A hook installed by the build script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstalledHook {
+    pub name: String,
+    /// The hook script is generated from the configuration. Otherwise it is a user hook
+    pub generated: bool,
+    /// Hash of the content of the script. `None` when the content is not fixed at installation
+    /// (e.g. a symlink to a user hook or a hook which was not installed)
+    pub hash: Option<String>,
+}
+
+/// Hooks installed in the hooks directory and the configuration they were installed with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallRecord {
+    pub manifest: PathBuf,
+    pub features: Vec<String>,
+    pub config_hash: String,
+    pub hooks: Vec<InstalledHook>,
+}
+
+fn file_hash(path: &Path) -> Result<String> {
+    Ok(stable_hash(&fs::read(path)?))
+}
+
+// Path of the script generated for the hook. It is renamed when merged into the hook script of
+// another hook manager
+fn generated_script_path(hooks_dir: &Path, hook: &str) -> PathBuf {
+    let path = hooks_dir.join(hook);
+    let merged = merged_hook_path(&path);
+    if merged.is_file() {
+        merged
+    } else {
+        path
+    }
+}
+
+fn get_str<'a>(table: &'a Table, key: &str) -> std::result::Result<&'a str, String> {
+    match table.get(key) {
+        Some(Value::String(s)) => Ok(s),
+        _ => Err(format!("'{}' must be a string", key)),
+    }
+}
+
+fn parse_hook(table: &Table) -> std::result::Result<InstalledHook, String> {
+    let generated = match table.get("generated") {
+        None => false,
+        Some(Value::Boolean(b)) => *b,
+        Some(_) => return Err("'generated' must be a boolean".to_string()),
+    };
+    let hash = match table.get("hash") {
+        None => None,
+        Some(Value::String(s)) => Some(s.clone()),
+        Some(_) => return Err("'hash' must be a string".to_string()),
+    };
+    Ok(InstalledHook {
+        name: get_str(table, "name")?.to_string(),
+        generated,
+        hash,
+    })
+}
+
+impl InstallRecord {
+    /// Record the hooks in the hooks directory. The generated hooks are recorded only when they
+    /// were generated by cargo-husky.
+    pub fn scan(
+        hooks_dir: &Path,
+        config: &Config,
+        generated: &[&str],
+        user: &[String],
+    ) -> Result<InstallRecord> {
+        let manifest = config
+            .manifest_dir()
+            .map(|d| d.join("Cargo.toml"))
+            .unwrap_or_default();
+        let mut hooks = vec![];
+        for hook in generated {
+            let path = generated_script_path(hooks_dir, hook);
+            let hash = match path.is_file() {
+                true if is_managed_hook(&path)?.is_some() => Some(file_hash(&path)?),
+                _ => None,
+            };
+            hooks.push(InstalledHook {
+                name: hook.to_string(),
+                generated: true,
+                hash,
+            });
+        }
+        for hook in user.iter().filter(|h| !generated.contains(&h.as_str())) {
+            let path = hooks_dir.join(hook);
+            // Symlinked user hooks follow edits of their sources
+            let hash = match fs::symlink_metadata(&path) {
+                Ok(m) if m.is_file() => Some(file_hash(&path)?),
+                _ => None,
+            };
+            hooks.push(InstalledHook {
+                name: hook.clone(),
+                generated: false,
+                hash,
+            });
+        }
+        Ok(InstallRecord {
+            manifest,
+            features: config.features().to_vec(),
+            config_hash: config.hash(),
+            hooks,
+        })
+    }
+
+    /// Render the record as TOML.
+    pub fn render(&self) -> Result<String> {
+        let manifest = match self.manifest.to_str() {
+            Some(path) => path,
+            None => return Err(Error::NonUtf8Path(self.manifest.clone())),
+        };
+        let features = self
+            .features
+            .iter()
+            .map(|f| string_literal(f))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut s = format!(
+            "# Hooks installed by cargo-husky v{}. Do not edit\nformat = {}\nmanifest = {}\nfeatures = [{}]\nconfig-hash = {}\n",
+            env!("CARGO_PKG_VERSION"),
+            RECORD_FORMAT,
+            string_literal(manifest),
+            features,
+            string_literal(&self.config_hash)
+        );
+        for hook in &self.hooks {
+            s += "\n[[hooks]]\n";
+            s += &format!("name = {}\n", string_literal(&hook.name));
+            if hook.generated {
+                s += "generated = true\n";
+            }
+            if let Some(hash) = &hook.hash {
+                s += &format!("hash = {}\n", string_literal(hash));
+            }
+        }
+        Ok(s)
+    }
+
+    pub fn parse(src: &str) -> std::result::Result<InstallRecord, String> {
+        let root = toml::parse(src)?;
+        match root.get("format") {
+            Some(Value::Integer(RECORD_FORMAT)) => {}
+            _ => return Err("Unsupported format. Please update cargo-husky command".to_string()),
+        }
+        let features = match root.get("features") {
+            Some(Value::Array(elems)) => elems
+                .iter()
+                .map(|e| match e {
+                    Value::String(s) => Ok(s.clone()),
+                    _ => Err("'features' must be an array of strings".to_string()),
+                })
+                .collect::<std::result::Result<Vec<_>, _>>()?,
+            _ => return Err("'features' must be an array of strings".to_string()),
+        };
+        let mut hooks = vec![];
+        match root.get("hooks") {
+            None => {}
+            Some(Value::Array(elems)) => {
+                for elem in elems {
+                    match elem {
+                        Value::Table(t) => hooks.push(parse_hook(t)?),
+                        _ => return Err("'hooks' must be an array of tables".to_string()),
+                    }
+                }
+            }
+            Some(_) => return Err("'hooks' must be an array of tables".to_string()),
+        }
+        Ok(InstallRecord {
+            manifest: PathBuf::from(get_str(&root, "manifest")?),
+            features,
+            config_hash: get_str(&root, "config-hash")?.to_string(),
+            hooks,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<InstallRecord> {
+        let src = fs::read_to_string(path)
+            .map_err(|e| Error::InvalidInstallRecord(path.to_owned(), e.to_string()))?;
+        InstallRecord::parse(&src).map_err(|msg| Error::InvalidInstallRecord(path.to_owned(), msg))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        Ok(write_file_if_changed(path, &self.render()?)?)
+    }
+}
+
+/// Difference between an installed hook and the current configuration found by `verify_hooks`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookDrift {
+    /// The hook script does not exist
+    Missing(String),
+    /// The hook script was not generated by cargo-husky
+    Foreign(String),
+    /// The hook script was edited after it was installed
+    Tampered(String),
+    /// The hook script was generated with another configuration
+    Stale(String),
+}
+
+impl fmt::Display for HookDrift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HookDrift::Missing(hook) => write!(f, "{}: Hook script is missing", hook),
+            HookDrift::Foreign(hook) => {
+                write!(f, "{}: Hook script was not generated by cargo-husky", hook)
+            }
+            HookDrift::Tampered(hook) => {
+                write!(f, "{}: Hook script was modified after installation", hook)
+            }
+            HookDrift::Stale(hook) => write!(
+                f,
+                "{}: Hook script was generated with configuration different from Cargo.toml",
+                hook
+            ),
+        }
+    }
+}
+
+/// Compare the hooks installed in `hooks_dir` with the current configuration in Cargo.toml. An empty
+/// vector is returned when all hooks are up-to-date. It fails when no hook was installed in the
+/// directory by the build script.
+pub fn verify_hooks(hooks_dir: &Path) -> Result<Vec<HookDrift>> {
+    let path = record_path(hooks_dir);
+    if !path.is_file() {
+        return Err(Error::HooksNotInstalled(hooks_dir.to_owned()));
+    }
+    let record = InstallRecord::load(&path)?;
+    let config_hash = Config::load(&record.manifest)?
+        .with_features(record.features.clone())
+        .hash();
+    let mut drifts = vec![];
+    for hook in &record.hooks {
+        let name = hook.name.clone();
+        let path = if hook.generated {
+            generated_script_path(hooks_dir, &hook.name)
+        } else {
+            hooks_dir.join(&hook.name)
+        };
+        // A symlink to a removed user hook is also missing
+        if !path.exists() {
+            drifts.push(HookDrift::Missing(name));
+            continue;
+        }
+        if hook.generated {
+            match is_managed_hook(&path)? {
+                None => {
+                    drifts.push(HookDrift::Foreign(name));
+                    continue;
+                }
+                Some(info) if info.config_hash.as_ref() != Some(&config_hash) => {
+                    drifts.push(HookDrift::Stale(name));
+                    continue;
+                }
+                Some(_) => {}
+            }
+        }
+        if let Some(hash) = &hook.hash {
+            if *hash != file_hash(&path)? {
+                drifts.push(HookDrift::Tampered(name));
+            }
+        }
+    }
+    Ok(drifts)
+}
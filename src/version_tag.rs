@@ -0,0 +1,112 @@
+use error::Result;
+use git::show;
+use std::path::Path;
+use toml::{self, Value};
+
+/// Pushed release tag which does not match the files at the tagged commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagMismatch {
+    /// Version in the manifest is not the version of the tag. `None` means no version is found.
+    Version {
+        tag: String,
+        manifest: String,
+        version: Option<String>,
+    },
+    /// Changelog has no entry for the version of the tag.
+    Changelog { tag: String, changelog: String },
+}
+
+fn is_numeric(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Version of the release tag such as `v1.2.3` or `1.2.3-beta.1`. Tags which are not versions
+/// return `None`.
+pub fn tag_version(tag: &str) -> Option<&str> {
+    let version = tag.strip_prefix('v').unwrap_or(tag);
+    let core = version.split(['-', '+']).next()?;
+    let parts = core.split('.').collect::<Vec<_>>();
+    if parts.len() == 3 && parts.iter().all(|p| is_numeric(p)) {
+        Some(version)
+    } else {
+        None
+    }
+}
+
+/// Version of the package in the manifest. `version.workspace = true` and virtual manifests use
+/// `[workspace.package]` version.
+pub fn manifest_version(manifest: &str) -> Option<String> {
+    let manifest = toml::parse(manifest).ok()?;
+    for path in &[
+        &["package", "version"][..],
+        &["workspace", "package", "version"],
+    ] {
+        if let Some(Value::String(version)) = toml::lookup(&manifest, path) {
+            return Some(version.clone());
+        }
+    }
+    None
+}
+
+/// Whether the changelog mentions the version. `1.2.3` does not match `1.2.30` or `1.2.3-beta`.
+pub fn changelog_has_entry(changelog: &str, version: &str) -> bool {
+    changelog.match_indices(version).any(|(i, _)| {
+        let before = changelog[..i].chars().next_back();
+        let after = changelog[i + version.len()..].chars().next();
+        !matches!(before, Some(c) if c.is_ascii_digit() || c == '.')
+            && !matches!(after, Some(c) if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'))
+    })
+}
+
+/// Check release tags pushed to the repository at `dir`. `input` is the stdin of `pre-push` hook.
+/// Paths of the manifest and the changelog are relative to the repository root. The changelog is
+/// not checked when it is `None` or does not exist at the tagged commit. Deleted tags and tags which
+/// are not versions are ignored.
+pub fn check_pushed_tags(
+    dir: &Path,
+    input: &str,
+    manifest: &str,
+    changelog: Option<&str>,
+) -> Result<Vec<TagMismatch>> {
+    let mut mismatches = vec![];
+    for line in input.lines() {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        let (local_sha, remote_ref) = match fields.as_slice() {
+            [_, local_sha, remote_ref, _] => (*local_sha, *remote_ref),
+            _ => continue,
+        };
+        if local_sha.bytes().all(|b| b == b'0') {
+            continue;
+        }
+        let tag = match remote_ref.strip_prefix("refs/tags/") {
+            Some(tag) => tag,
+            None => continue,
+        };
+        let version = match tag_version(tag) {
+            Some(version) => version,
+            None => continue,
+        };
+        let found = show(dir, local_sha, manifest)?
+            .as_deref()
+            .and_then(manifest_version);
+        if found.as_deref() != Some(version) {
+            mismatches.push(TagMismatch::Version {
+                tag: tag.to_string(),
+                manifest: manifest.to_string(),
+                version: found,
+            });
+            continue;
+        }
+        if let Some(changelog) = changelog {
+            if let Some(content) = show(dir, local_sha, changelog)? {
+                if !changelog_has_entry(&content, version) {
+                    mismatches.push(TagMismatch::Changelog {
+                        tag: tag.to_string(),
+                        changelog: changelog.to_string(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(mismatches)
+}